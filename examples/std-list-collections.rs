@@ -5,7 +5,10 @@ use std::{
 };
 
 use io_fs::runtimes::std::handle;
-use io_vdir::coroutines::list_collections::{ListCollections, ListCollectionsResult};
+use io_vdir::{
+    coroutines::list_collections::{ListCollections, ListCollectionsResult},
+    runtime::HandBack,
+};
 
 fn main() {
     let _ = env_logger::try_init();
@@ -15,6 +18,7 @@ fn main() {
         Err(_) => read_line("Collections home path?").into(),
     };
 
+    let mut runtime = HandBack::new(handle);
     let mut arg = None;
     let mut coroutine = ListCollections::new(&path);
 
@@ -22,7 +26,7 @@ fn main() {
         match coroutine.resume(arg) {
             ListCollectionsResult::Ok(collections) => break collections,
             ListCollectionsResult::Err(err) => panic!("{err}"),
-            ListCollectionsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListCollectionsResult::Io(io) => arg = Some(runtime.handle(io).unwrap()),
         }
     };
 
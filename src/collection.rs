@@ -72,3 +72,59 @@ impl AsRef<Path> for Collection {
         &self.path
     }
 }
+
+/// The Vdir collection metadata patch.
+///
+/// Describes, for each metadata of a collection, whether it should
+/// be kept as it is, set to a new value or unset.
+///
+/// See [`crate::coroutines::update_collection::UpdateCollection`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CollectionPatch {
+    /// The patch of the collection's display name.
+    pub display_name: MetadataPatch,
+
+    /// The patch of the collection's description.
+    pub description: MetadataPatch,
+
+    /// The patch of the collection's color.
+    pub color: MetadataPatch,
+}
+
+impl From<Collection> for CollectionPatch {
+    /// Builds a patch that sets the given collection's metadata, and
+    /// keeps the missing ones.
+    fn from(collection: Collection) -> Self {
+        Self {
+            display_name: collection.display_name.into(),
+            description: collection.description.into(),
+            color: collection.color.into(),
+        }
+    }
+}
+
+/// The Vdir collection metadata patch operation.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum MetadataPatch {
+    /// The metadata file is left untouched.
+    #[default]
+    Keep,
+
+    /// The metadata file is created or replaced with the given
+    /// contents.
+    Set(String),
+
+    /// The metadata file is removed, if it exists.
+    Unset,
+}
+
+impl From<Option<String>> for MetadataPatch {
+    /// Maps `Some` to [`MetadataPatch::Set`] and `None` to
+    /// [`MetadataPatch::Keep`].
+    fn from(value: Option<String>) -> Self {
+        match value {
+            Some(value) => Self::Set(value),
+            None => Self::Keep,
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Module dedicated to the resolution of synchronization conflicts.
+
+use std::{cmp::Ordering, fmt, sync::Arc};
+
+use uuid::Uuid;
+
+use crate::item::{Item, ItemKind};
+
+/// The user-supplied conflict resolver.
+///
+/// Receives the conflicting items of the side A and of the side B,
+/// and decides the outcome of the conflict.
+pub type ConflictResolver = Arc<dyn Fn(&Item, &Item) -> ConflictResolution + Send + Sync>;
+
+/// The strategy used to resolve conflicts.
+///
+/// A conflict occurs when the same item has been modified on both
+/// sides since the last synchronization.
+///
+/// See [`crate::coroutines::sync_collection::SyncCollection`].
+#[derive(Clone, Default)]
+pub enum ConflictStrategy {
+    /// Conflicts are reported, and conflicting items are left
+    /// untouched.
+    #[default]
+    Report,
+
+    /// The item of the side A always wins.
+    KeepA,
+
+    /// The item of the side B always wins.
+    KeepB,
+
+    /// The most recently modified item wins.
+    ///
+    /// Relies on the LAST-MODIFIED property for iCalendar items, and
+    /// on the REV property for vCard items. Conflicts are reported
+    /// when the modification dates are missing or equal.
+    Newest,
+
+    /// Both items are kept.
+    ///
+    /// The item of the side A keeps its UID, while the item of the
+    /// side B is duplicated with a new UID.
+    KeepBoth,
+
+    /// The outcome is decided by the given resolver.
+    Custom(ConflictResolver),
+}
+
+impl ConflictStrategy {
+    /// Creates a custom strategy from the given resolver.
+    pub fn custom(
+        resolver: impl Fn(&Item, &Item) -> ConflictResolution + Send + Sync + 'static,
+    ) -> Self {
+        Self::Custom(Arc::new(resolver))
+    }
+
+    /// Resolves the conflict between the given items of the side A
+    /// and of the side B.
+    pub fn resolve(&self, a: &Item, b: &Item) -> ConflictResolution {
+        match self {
+            Self::Report => ConflictResolution::Unresolved,
+            Self::KeepA => ConflictResolution::KeepA,
+            Self::KeepB => ConflictResolution::KeepB,
+            Self::Newest => {
+                let (Some(date_a), Some(date_b)) = (a.kind.last_modified(), b.kind.last_modified())
+                else {
+                    return ConflictResolution::Unresolved;
+                };
+
+                match date_a.cmp(&date_b) {
+                    Ordering::Greater => ConflictResolution::KeepA,
+                    Ordering::Less => ConflictResolution::KeepB,
+                    Ordering::Equal => ConflictResolution::Unresolved,
+                }
+            }
+            Self::KeepBoth => {
+                let mut kind = b.kind.clone();
+                kind.set_uid(Uuid::new_v4());
+                ConflictResolution::KeepBoth(kind)
+            }
+            Self::Custom(resolver) => resolver(a, b),
+        }
+    }
+}
+
+impl fmt::Debug for ConflictStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Report => write!(f, "Report"),
+            Self::KeepA => write!(f, "KeepA"),
+            Self::KeepB => write!(f, "KeepB"),
+            Self::Newest => write!(f, "Newest"),
+            Self::KeepBoth => write!(f, "KeepBoth"),
+            Self::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// The outcome of a conflict resolution.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConflictResolution {
+    /// The conflict could not be resolved: it is reported, and
+    /// conflicting items are left untouched.
+    Unresolved,
+
+    /// The item of the side A replaces the item of the side B.
+    KeepA,
+
+    /// The item of the side B replaces the item of the side A.
+    KeepB,
+
+    /// The item of the side A replaces the item of the side B, and
+    /// the given item is created on both sides.
+    ///
+    /// The given item is expected to have a different UID than the
+    /// conflicting items, typically the item of the side B with a
+    /// new UID.
+    KeepBoth(ItemKind),
+
+    /// The given item replaces the items of both sides.
+    Merge(ItemKind),
+}
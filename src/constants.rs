@@ -1,5 +1,7 @@
 //! Module dedicated to Vdir constants.
 
+#![allow(clippy::redundant_static_lifetimes)]
+
 /// The display name of the collection.
 ///
 /// Represents the name of the file containing the display name of the
@@ -26,3 +28,9 @@ pub const VCF: &'static str = "vcf";
 
 /// The ICS file extension, used by iCalendar files.
 pub const ICS: &'static str = "ics";
+
+/// The index of the collection.
+///
+/// Represents the name of the hidden file containing the index of
+/// the collection, see [`crate::index`].
+pub const INDEX: &'static str = ".io-vdir-index";
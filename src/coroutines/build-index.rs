@@ -0,0 +1,216 @@
+//! I/O-free coroutine to build a Vdir collection index.
+
+use std::{
+    collections::HashMap,
+    mem,
+    path::{Path, PathBuf},
+};
+
+use io_fs::{
+    coroutines::{
+        create_file::CreateFile, read_dir::ReadDir, read_files::ReadFiles, rename::Rename,
+    },
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use log::debug;
+use thiserror::Error;
+
+use crate::{
+    constants::INDEX,
+    coroutines::read_item::{parse_item, ReadItemError},
+    etag::Etag,
+    index::{Index, IndexEntry},
+    item::ItemFormat,
+    tmp::tmp_path,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum BuildIndexError {
+    /// An error occured during the collection directory listing.
+    #[error("List Vdir items error")]
+    ReadDirError(#[source] FsError),
+
+    /// An error occured during the items reading.
+    #[error("Read Vdir items error")]
+    ReadFilesError(#[source] FsError),
+
+    /// An error occured during the creation of the temporary index
+    /// file.
+    #[error("Create temporary Vdir index file error")]
+    CreateTempFile(#[source] FsError),
+
+    /// An error occured during the switch between old and new index
+    /// files.
+    #[error("Save Vdir index file error")]
+    SaveFile(#[source] FsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum BuildIndexResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the saved index, as well as the parsing errors of
+    /// the items that could not be parsed (hence not indexed),
+    /// indexed by path.
+    Ok(Index, HashMap<PathBuf, ReadItemError>),
+
+    /// The coroutine encountered an error.
+    Err(BuildIndexError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListItems(ReadDir),
+    ReadItems(ReadFiles),
+    CreateTempIndex(CreateFile),
+    MoveIndex(Rename),
+}
+
+/// I/O-free coroutine to build a Vdir collection index.
+///
+/// Every item of the collection is read in order to compute its
+/// etag. Only the items whose etag is missing from the previous
+/// index are parsed, see [`BuildIndex::from_index`]. The index is
+/// then saved inside the collection, see
+/// [`crate::constants::INDEX`].
+#[derive(Debug)]
+pub struct BuildIndex {
+    path: PathBuf,
+    path_tmp: PathBuf,
+    index: Index,
+    errors: HashMap<PathBuf, ReadItemError>,
+    state: State,
+}
+
+impl BuildIndex {
+    /// Creates a new coroutine from the given collection path,
+    /// building the index from scratch.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self::from_index(path, Index::default())
+    }
+
+    /// Creates a new coroutine from the given collection path,
+    /// reusing the entries of the given previous index whose etag
+    /// did not change.
+    pub fn from_index(path: impl AsRef<Path>, index: Index) -> Self {
+        let path = path.as_ref();
+        let fs = ReadDir::new(path);
+        let state = State::ListItems(fs);
+        let path = path.join(INDEX);
+
+        Self {
+            path_tmp: tmp_path(&path),
+            path,
+            index,
+            errors: HashMap::new(),
+            state,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> BuildIndexResult {
+        loop {
+            match &mut self.state {
+                State::ListItems(fs) => {
+                    let mut paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break BuildIndexResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = BuildIndexError::ReadDirError(err);
+                            break BuildIndexResult::Err(err);
+                        }
+                    };
+
+                    paths.retain(|path| ItemFormat::from_path(path).is_some());
+
+                    let fs = ReadFiles::new(paths);
+                    self.state = State::ReadItems(fs);
+                }
+                State::ReadItems(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break BuildIndexResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = BuildIndexError::ReadFilesError(err);
+                            break BuildIndexResult::Err(err);
+                        }
+                    };
+
+                    self.index = self.update(contents);
+
+                    let fs = CreateFile::new(&self.path_tmp, self.index.to_bytes());
+                    self.state = State::CreateTempIndex(fs);
+                }
+                State::CreateTempIndex(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break BuildIndexResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = BuildIndexError::CreateTempFile(err);
+                            break BuildIndexResult::Err(err);
+                        }
+                    };
+
+                    let fs = Rename::new(Some((&self.path_tmp, &self.path)));
+                    self.state = State::MoveIndex(fs);
+                }
+                State::MoveIndex(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break BuildIndexResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = BuildIndexError::SaveFile(err);
+                            break BuildIndexResult::Err(err);
+                        }
+                    };
+
+                    let index = mem::take(&mut self.index);
+                    let errors = mem::take(&mut self.errors);
+                    break BuildIndexResult::Ok(index, errors);
+                }
+            }
+        }
+    }
+
+    /// Builds the new index from the given items contents, reusing
+    /// unchanged entries of the previous index.
+    fn update(&mut self, contents: HashMap<PathBuf, Vec<u8>>) -> Index {
+        let mut previous = mem::take(&mut self.index).entries;
+        let mut entries = HashMap::new();
+
+        for (path, contents) in contents {
+            let Some(href) = path.file_name() else {
+                continue;
+            };
+
+            let href = href.to_string_lossy().to_string();
+            let etag = Etag::from_contents(&contents);
+
+            if let Some(entry) = previous.remove(&href) {
+                if entry.etag == etag {
+                    entries.insert(href, entry);
+                    continue;
+                }
+            }
+
+            match parse_item(path.clone(), contents) {
+                Ok(item) => {
+                    entries.insert(href, IndexEntry::from_item(&item));
+                }
+                Err(err) => {
+                    debug!("cannot index Vdir item at {}: {err}", path.display());
+                    self.errors.insert(path, err);
+                }
+            }
+        }
+
+        Index { entries }
+    }
+}
@@ -1,20 +1,54 @@
 //! I/O-free coroutine to create a Vdir item.
 
+use std::path::{Path, PathBuf};
+
 use io_fs::{
-    coroutines::create_file::CreateFile,
+    coroutines::{
+        create_file::CreateFile, read_dir::ReadDir, read_files::ReadFiles, rename::Rename,
+    },
     error::{FsError, FsResult},
     io::FsIo,
 };
 use thiserror::Error;
 
-use crate::item::Item;
+use crate::{
+    coroutines::read_item::parse_item,
+    item::{Item, ItemFormat},
+    tmp::tmp_path,
+};
 
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
 pub enum CreateItemError {
-    /// An error occured during the file creation.
+    /// An error occured during the collection directory listing.
+    #[error("List Vdir collection items error")]
+    ReadDirError(#[source] FsError),
+
+    /// The Vdir item file already exists.
+    ///
+    /// This check is best-effort, see [`CreateItem`].
+    #[error("Vdir item already exists at {0}")]
+    AlreadyExists(PathBuf),
+
+    /// An error occured during the reading of the collection items.
+    #[error("Read Vdir collection items error")]
+    ReadFilesError(#[source] FsError),
+
+    /// Another Vdir item already has the same UID.
+    ///
+    /// Contains the UID and the path of the existing item, which can
+    /// be updated instead.
+    #[error("Vdir item with UID {0} already exists at {1}")]
+    UidAlreadyExists(String, PathBuf),
+
+    /// An error occured during the temporary file creation.
     #[error("Create Vdir item error")]
-    CreateFileError(#[from] FsError),
+    CreateFileError(#[source] FsError),
+
+    /// An error occured during the move of the temporary file to
+    /// its final path.
+    #[error("Save Vdir item file error")]
+    RenameFileError(#[source] FsError),
 }
 
 /// Output emitted when the coroutine terminates its progression.
@@ -31,23 +65,196 @@ pub enum CreateItemResult {
     Io(FsIo),
 }
 
+#[derive(Debug)]
+enum State {
+    ListItems(ReadDir),
+    CheckUid(ReadFiles),
+    CreateTempItem(CreateFile),
+    MoveItem(Rename),
+}
+
 /// I/O-free coroutine to create a Vdir item.
+///
+/// As recommended by the Vdir standard, the item is first written
+/// into a temporary file, then moved to its final path. The
+/// coroutine fails if an item already exists at the final path, or
+/// optionally if another item already has the same UID.
+///
+/// These checks are best-effort, not atomic: they rely on a listing
+/// of the collection made before the move, and the move overwrites
+/// its target. An item created concurrently at the same path between
+/// the listing and the move is therefore overwritten. The listing
+/// also makes each creation cost a full directory read (plus a read
+/// of every item when checking UIDs): prefer
+/// [`crate::coroutines::mirror_collection::MirrorCollection`] to
+/// write many items at once.
 #[derive(Debug)]
-pub struct CreateItem(CreateFile);
+pub struct CreateItem {
+    item: Item,
+    auto_uid: bool,
+    uid_file_name: bool,
+    unique_uid: bool,
+    path_tmp: PathBuf,
+    state: State,
+}
 
 impl CreateItem {
     /// Creates a new coroutine from the given item.
     pub fn new(item: Item) -> Self {
-        let bytes = item.to_string().into_bytes();
-        Self(CreateFile::new(item.path, bytes))
+        let path_tmp = tmp_path(&item.path);
+        let dir = item.path.parent().unwrap_or(Path::new(""));
+        let state = State::ListItems(ReadDir::new(dir));
+
+        Self {
+            item,
+            auto_uid: false,
+            uid_file_name: false,
+            unique_uid: false,
+            path_tmp,
+            state,
+        }
+    }
+
+    /// Assigns a generated UID to the item if it lacks one, before
+    /// saving it.
+    ///
+    /// See [`crate::item::ItemKind::ensure_uid`].
+    pub fn with_auto_uid(mut self, auto_uid: bool) -> Self {
+        self.auto_uid = auto_uid;
+        self.prepare();
+        self
+    }
+
+    /// Names the item file after the item's UID, as recommended by
+    /// the Vdir standard.
+    ///
+    /// The item keeps its path when it has no UID. The final path
+    /// can be retrieved with [`CreateItem::path`].
+    ///
+    /// See [`Item::rename_after_uid`].
+    pub fn with_uid_file_name(mut self, uid_file_name: bool) -> Self {
+        self.uid_file_name = uid_file_name;
+        self.prepare();
+        self
+    }
+
+    /// Ensures that no other item of the collection has the same UID
+    /// as the item.
+    ///
+    /// All the items of the collection need to be read and parsed,
+    /// which is why this check is opt-in. Items that cannot be parsed
+    /// are ignored.
+    pub fn with_unique_uid(mut self, unique_uid: bool) -> Self {
+        self.unique_uid = unique_uid;
+        self
+    }
+
+    /// Returns the final path of the item.
+    pub fn path(&self) -> &Path {
+        &self.item.path
+    }
+
+    /// Returns the item, as it will be saved.
+    pub fn item(&self) -> &Item {
+        &self.item
+    }
+
+    fn prepare(&mut self) {
+        if self.auto_uid {
+            self.item.kind.ensure_uid();
+        }
+
+        if self.uid_file_name {
+            self.item.rename_after_uid();
+        }
+
+        self.path_tmp = tmp_path(&self.item.path);
     }
 
     /// Makes the coroutine progress.
-    pub fn resume(&mut self, arg: Option<FsIo>) -> CreateItemResult {
-        match self.0.resume(arg) {
-            FsResult::Ok(()) => CreateItemResult::Ok,
-            FsResult::Err(err) => CreateItemResult::Err(err.into()),
-            FsResult::Io(io) => CreateItemResult::Io(io),
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> CreateItemResult {
+        loop {
+            match &mut self.state {
+                State::ListItems(fs) => {
+                    let paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break CreateItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = CreateItemError::ReadDirError(err);
+                            break CreateItemResult::Err(err);
+                        }
+                    };
+
+                    if paths.contains(&self.item.path) {
+                        let err = CreateItemError::AlreadyExists(self.item.path.clone());
+                        break CreateItemResult::Err(err);
+                    }
+
+                    if self.unique_uid && self.item.kind.uid().is_some() {
+                        let mut paths = paths;
+                        paths.retain(|path| ItemFormat::from_path(path).is_some());
+                        let fs = ReadFiles::new(paths);
+                        self.state = State::CheckUid(fs);
+                        continue;
+                    }
+
+                    self.state = self.create_temp_item();
+                }
+                State::CheckUid(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break CreateItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = CreateItemError::ReadFilesError(err);
+                            break CreateItemResult::Err(err);
+                        }
+                    };
+
+                    let uid = self.item.kind.uid();
+
+                    let existing_item = contents
+                        .into_iter()
+                        .filter_map(|(path, contents)| parse_item(path, contents).ok())
+                        .find(|item| item.kind.uid() == uid);
+
+                    if let Some(existing_item) = existing_item {
+                        let uid = uid.unwrap_or_default().to_owned();
+                        let err = CreateItemError::UidAlreadyExists(uid, existing_item.path);
+                        break CreateItemResult::Err(err);
+                    }
+
+                    self.state = self.create_temp_item();
+                }
+                State::CreateTempItem(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break CreateItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = CreateItemError::CreateFileError(err);
+                            break CreateItemResult::Err(err);
+                        }
+                    };
+
+                    let fs = Rename::new(Some((&self.path_tmp, &self.item.path)));
+                    self.state = State::MoveItem(fs);
+                }
+                State::MoveItem(fs) => {
+                    break match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => CreateItemResult::Ok,
+                        FsResult::Io(io) => CreateItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = CreateItemError::RenameFileError(err);
+                            CreateItemResult::Err(err)
+                        }
+                    };
+                }
+            }
         }
     }
+
+    fn create_temp_item(&self) -> State {
+        let contents = self.item.to_bytes();
+        let fs = CreateFile::new(&self.path_tmp, contents);
+        State::CreateTempItem(fs)
+    }
 }
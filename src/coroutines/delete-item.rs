@@ -1,20 +1,31 @@
 //! I/O-free coroutine to delete a Vdir item.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use io_fs::{
-    coroutines::remove_file::RemoveFile,
+    coroutines::{read_file::ReadFile, remove_file::RemoveFile},
     error::{FsError, FsResult},
     io::FsIo,
 };
 use thiserror::Error;
 
+use crate::etag::Etag;
+
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
 pub enum DeleteItemError {
+    /// An error occured while reading the current item file.
+    #[error("Read current Vdir item file error")]
+    ReadFileError(#[source] FsError),
+
+    /// The current item file does not match the expected entity
+    /// tag: it has been changed by someone else in the meantime.
+    #[error("Vdir item at {0} has been modified in the meantime")]
+    PreconditionFailed(PathBuf),
+
     /// An error occured during the file deletion.
     #[error("Delete Vdir item error")]
-    RemoveFileError(#[from] FsError),
+    RemoveFileError(#[source] FsError),
 }
 
 /// Output emitted when the coroutine terminates its progression.
@@ -31,22 +42,81 @@ pub enum DeleteItemResult {
     Io(FsIo),
 }
 
+#[derive(Debug)]
+enum State {
+    CheckEtag(ReadFile, Etag),
+    RemoveItem(RemoveFile),
+}
+
 /// I/O-free coroutine to delete a Vdir item.
 #[derive(Debug)]
-pub struct DeleteItem(RemoveFile);
+pub struct DeleteItem {
+    path: PathBuf,
+    state: State,
+}
 
 impl DeleteItem {
-    /// Creates a new coroutine from the given collection's path.
-    pub fn new(path: impl AsRef<Path>) -> Self {
-        Self(RemoveFile::new(path.as_ref()))
+    /// Creates a new coroutine from the given item's path.
+    ///
+    /// When an entity tag is given, the coroutine checks that the
+    /// current item file still matches it right before removing it,
+    /// and fails with [`DeleteItemError::PreconditionFailed`]
+    /// otherwise (similar to the HTTP `If-Match` header).
+    ///
+    /// A current item file that cannot be read, for example because
+    /// it has been deleted in the meantime, fails the precondition
+    /// as well: resume the coroutine with the unprocessed request
+    /// ([`FsIo::ReadFile`] holding the item path) when the runtime
+    /// fails to read it (see [`crate::runtime::HandBack`]).
+    pub fn new(path: impl AsRef<Path>, etag: Option<Etag>) -> Self {
+        let path = path.as_ref().to_owned();
+
+        let state = match etag {
+            Some(etag) => State::CheckEtag(ReadFile::new(&path), etag),
+            None => State::RemoveItem(RemoveFile::new(&path)),
+        };
+
+        Self { path, state }
     }
 
     /// Makes the coroutine progress.
-    pub fn resume(&mut self, arg: Option<FsIo>) -> DeleteItemResult {
-        match self.0.resume(arg) {
-            FsResult::Ok(()) => DeleteItemResult::Ok,
-            FsResult::Err(err) => DeleteItemResult::Err(err.into()),
-            FsResult::Io(io) => DeleteItemResult::Io(io),
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> DeleteItemResult {
+        loop {
+            match &mut self.state {
+                State::CheckEtag(fs, etag) => {
+                    if matches!(&arg, Some(FsIo::ReadFile(Err(failed))) if *failed == self.path) {
+                        let err = DeleteItemError::PreconditionFailed(self.path.clone());
+                        break DeleteItemResult::Err(err);
+                    }
+
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break DeleteItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = DeleteItemError::ReadFileError(err);
+                            break DeleteItemResult::Err(err);
+                        }
+                    };
+
+                    if *etag != Etag::from_contents(contents) {
+                        let err = DeleteItemError::PreconditionFailed(self.path.clone());
+                        break DeleteItemResult::Err(err);
+                    }
+
+                    let fs = RemoveFile::new(&self.path);
+                    self.state = State::RemoveItem(fs);
+                }
+                State::RemoveItem(fs) => {
+                    break match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => DeleteItemResult::Ok,
+                        FsResult::Io(io) => DeleteItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = DeleteItemError::RemoveFileError(err);
+                            DeleteItemResult::Err(err)
+                        }
+                    };
+                }
+            }
         }
     }
 }
@@ -0,0 +1,200 @@
+//! I/O-free coroutine to find a Vdir item by its UID.
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use io_fs::{
+    coroutines::{read_dir::ReadDir, read_files::ReadFiles},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use log::debug;
+use thiserror::Error;
+
+use crate::{
+    coroutines::read_item::parse_item,
+    item::{uid_file_stem, Item, ItemFormat},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum FindItemByUidError {
+    /// An error occured during the collection directory listing.
+    #[error("List Vdir collection items error")]
+    ReadDirError(#[source] FsError),
+
+    /// An error occured during the items reading.
+    #[error("Read Vdir items error")]
+    ReadFilesError(#[source] FsError),
+
+    /// No item has the given UID.
+    #[error("Cannot find Vdir item with UID {0}")]
+    NotFound(String),
+
+    /// Multiple items share the given UID.
+    #[error("Multiple Vdir items share the UID {0}: {1:?}")]
+    Duplicated(String, Vec<PathBuf>),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum FindItemByUidResult {
+    /// The coroutine successfully terminated its progression.
+    Ok(Item),
+
+    /// The coroutine encountered an error.
+    Err(FindItemByUidError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListEntries(ReadDir),
+    ReadNamedItems(ReadFiles),
+    ScanItems(ReadFiles),
+}
+
+/// I/O-free coroutine to find a Vdir item by its UID.
+///
+/// Items named after their UID (see [`uid_file_stem`]) are read
+/// first. If none of them has the given UID, all the other items of
+/// the collection are read and parsed.
+///
+/// Duplicated UIDs are only detected when falling back to the full
+/// scan of the collection.
+#[derive(Debug)]
+pub struct FindItemByUid {
+    uid: String,
+    paths: HashSet<PathBuf>,
+    state: State,
+}
+
+impl FindItemByUid {
+    /// Creates a new coroutine from the given collection path and
+    /// UID.
+    pub fn new(path: impl AsRef<Path>, uid: impl ToString) -> Self {
+        let fs = ReadDir::new(path.as_ref());
+        let state = State::ListEntries(fs);
+
+        Self {
+            uid: uid.to_string(),
+            paths: HashSet::new(),
+            state,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> FindItemByUidResult {
+        loop {
+            match &mut self.state {
+                State::ListEntries(fs) => {
+                    let mut paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break FindItemByUidResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = FindItemByUidError::ReadDirError(err);
+                            break FindItemByUidResult::Err(err);
+                        }
+                    };
+
+                    paths.retain(|path| ItemFormat::from_path(path).is_some());
+
+                    let stem = uid_file_stem(&self.uid);
+
+                    let (uid_paths, paths): (HashSet<_>, HashSet<_>) = paths
+                        .into_iter()
+                        .partition(|path| path.file_stem().is_some_and(|s| *s == *stem));
+
+                    self.paths = paths;
+
+                    if uid_paths.is_empty() {
+                        let fs = ReadFiles::new(mem::take(&mut self.paths));
+                        self.state = State::ScanItems(fs);
+                        continue;
+                    }
+
+                    let fs = ReadFiles::new(uid_paths);
+                    self.state = State::ReadNamedItems(fs);
+                }
+                State::ReadNamedItems(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break FindItemByUidResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = FindItemByUidError::ReadFilesError(err);
+                            break FindItemByUidResult::Err(err);
+                        }
+                    };
+
+                    let mut items = self.matching_items(contents);
+
+                    if items.len() == 1 {
+                        break FindItemByUidResult::Ok(items.remove(0));
+                    }
+
+                    if !items.is_empty() {
+                        break self.duplicated(items);
+                    }
+
+                    debug!("no Vdir item named after UID {}, scan collection", self.uid);
+
+                    let fs = ReadFiles::new(mem::take(&mut self.paths));
+                    self.state = State::ScanItems(fs);
+                }
+                State::ScanItems(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break FindItemByUidResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = FindItemByUidError::ReadFilesError(err);
+                            break FindItemByUidResult::Err(err);
+                        }
+                    };
+
+                    let mut items = self.matching_items(contents);
+
+                    break match items.len() {
+                        0 => {
+                            let err = FindItemByUidError::NotFound(self.uid.clone());
+                            FindItemByUidResult::Err(err)
+                        }
+                        1 => FindItemByUidResult::Ok(items.remove(0)),
+                        _ => self.duplicated(items),
+                    };
+                }
+            }
+        }
+    }
+
+    /// Parses the given contents, and keeps the items having the
+    /// searched UID.
+    fn matching_items(&self, contents: HashMap<PathBuf, Vec<u8>>) -> Vec<Item> {
+        contents
+            .into_iter()
+            .filter_map(
+                |(path, contents)| match parse_item(path.clone(), contents) {
+                    Ok(item) => Some(item),
+                    Err(err) => {
+                        debug!("cannot parse Vdir item at {}: {err}", path.display());
+                        None
+                    }
+                },
+            )
+            .filter(|item| item.kind.uid() == Some(self.uid.as_str()))
+            .collect()
+    }
+
+    fn duplicated(&self, items: Vec<Item>) -> FindItemByUidResult {
+        let mut paths: Vec<_> = items.into_iter().map(|item| item.path).collect();
+        paths.sort();
+
+        let err = FindItemByUidError::Duplicated(self.uid.clone(), paths);
+        FindItemByUidResult::Err(err)
+    }
+}
@@ -1,7 +1,7 @@
 //! I/O-free coroutine to list Vdir collections.
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
 };
 
@@ -10,6 +10,7 @@ use io_fs::{
     error::{FsError, FsResult},
     io::FsIo,
 };
+use log::debug;
 use thiserror::Error;
 
 use crate::{
@@ -24,6 +25,10 @@ pub enum ListCollectionsError {
     #[error("List Vdir collections error")]
     ListDirsError(#[source] FsError),
 
+    /// An error occured during the collection entries listing.
+    #[error("List Vdir collection entries error")]
+    ListEntriesError(#[source] FsError),
+
     /// An error occured during the metadata file listing.
     #[error("Read Vdir collections' metadata error")]
     ListFilesError(#[source] FsError),
@@ -46,13 +51,28 @@ pub enum ListCollectionsResult {
 #[derive(Debug)]
 enum State {
     ListCollections(ReadDir),
-    ReadMetadataFiles(HashSet<PathBuf>, ReadFiles),
+    ListCollectionEntries(PathBuf, ReadDir),
+    ReadMetadataFiles(ReadFiles),
 }
 
 /// I/O-free coroutine to list Vdir collections.
+///
+/// Collections are the non-hidden directories of the root
+/// directory. Since the coroutine does not access the filesystem
+/// by itself, it discovers collections' metadata by listing their
+/// entries.
+///
+/// Runtimes cannot tell the coroutine the type of an entry: when
+/// listing an entry fails (for example because it is a regular file,
+/// with ENOTDIR), resume the coroutine with the unprocessed request
+/// ([`FsIo::ReadDir`] holding the entry path) to skip it. See
+/// [`crate::runtime::HandBack`].
 #[derive(Debug)]
 pub struct ListCollections {
     state: State,
+    pending_paths: Vec<PathBuf>,
+    collection_paths: HashSet<PathBuf>,
+    metadata_paths: HashSet<PathBuf>,
 }
 
 impl ListCollections {
@@ -61,7 +81,12 @@ impl ListCollections {
         let fs = ReadDir::new(root.as_ref());
         let state = State::ListCollections(fs);
 
-        Self { state }
+        Self {
+            state,
+            pending_paths: Vec::new(),
+            collection_paths: HashSet::new(),
+            metadata_paths: HashSet::new(),
+        }
     }
 
     /// Makes the coroutine progress.
@@ -69,7 +94,7 @@ impl ListCollections {
         loop {
             match &mut self.state {
                 State::ListCollections(fs) => {
-                    let mut collection_paths = match fs.resume(arg.take()) {
+                    let paths = match fs.resume(arg.take()) {
                         FsResult::Ok(paths) => paths,
                         FsResult::Io(io) => break ListCollectionsResult::Io(io),
                         FsResult::Err(err) => {
@@ -78,34 +103,38 @@ impl ListCollections {
                         }
                     };
 
-                    collection_paths.retain(|path| path.is_dir());
-
-                    let mut metadata_paths = HashSet::new();
-
-                    for dir in &collection_paths {
-                        let name_path = dir.join(DISPLAYNAME);
-
-                        if name_path.is_file() {
-                            metadata_paths.insert(name_path);
-                        }
-
-                        let desc_path = dir.join(DESCRIPTION);
+                    self.pending_paths = paths.into_iter().filter(|p| !is_hidden(p)).collect();
+                    self.state = self.next_state();
+                }
+                State::ListCollectionEntries(path, fs) => {
+                    if matches!(&arg, Some(FsIo::ReadDir(Err(failed))) if failed == path) {
+                        debug!("skip non-collection entry at {}", path.display());
+                        arg = None;
+                        self.state = self.next_state();
+                        continue;
+                    }
 
-                        if desc_path.is_file() {
-                            metadata_paths.insert(desc_path);
+                    let entries = match fs.resume(arg.take()) {
+                        FsResult::Ok(entries) => entries,
+                        FsResult::Io(io) => break ListCollectionsResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ListCollectionsError::ListEntriesError(err);
+                            break ListCollectionsResult::Err(err);
                         }
+                    };
 
-                        let color_path = dir.join(COLOR);
+                    for name in [DISPLAYNAME, DESCRIPTION, COLOR] {
+                        let metadata_path = path.join(name);
 
-                        if color_path.is_file() {
-                            metadata_paths.insert(color_path);
+                        if entries.contains(&metadata_path) {
+                            self.metadata_paths.insert(metadata_path);
                         }
                     }
 
-                    let flow = ReadFiles::new(metadata_paths);
-                    self.state = State::ReadMetadataFiles(collection_paths, flow);
+                    self.collection_paths.insert(path.clone());
+                    self.state = self.next_state();
                 }
-                State::ReadMetadataFiles(collection_paths, fs) => {
+                State::ReadMetadataFiles(fs) => {
                     let mut metadata = match fs.resume(arg.take()) {
                         FsResult::Ok(meta) => meta,
                         FsResult::Io(io) => break ListCollectionsResult::Io(io),
@@ -117,48 +146,18 @@ impl ListCollections {
 
                     let mut collections = HashSet::new();
 
-                    for path in collection_paths.clone() {
+                    for path in self.collection_paths.drain() {
                         let display_name = path.join(DISPLAYNAME);
                         let description = path.join(DESCRIPTION);
                         let color = path.join(COLOR);
 
-                        let mut collection = Collection {
+                        let collection = Collection {
                             path,
-                            display_name: None,
-                            description: None,
-                            color: None,
+                            display_name: read_metadata(&mut metadata, &display_name),
+                            description: read_metadata(&mut metadata, &description),
+                            color: read_metadata(&mut metadata, &color),
                         };
 
-                        if let Some(name) = &metadata.remove(&display_name) {
-                            let name = String::from_utf8_lossy(name);
-
-                            if name.trim().is_empty() {
-                                collection.display_name = None
-                            } else {
-                                collection.display_name = Some(name.to_string());
-                            }
-                        }
-
-                        if let Some(desc) = &metadata.remove(&description) {
-                            let desc = String::from_utf8_lossy(desc);
-
-                            if desc.trim().is_empty() {
-                                collection.description = None
-                            } else {
-                                collection.description = Some(desc.to_string());
-                            }
-                        }
-
-                        if let Some(color) = &metadata.remove(&color) {
-                            let color = String::from_utf8_lossy(color);
-
-                            if color.trim().is_empty() {
-                                collection.color = None
-                            } else {
-                                collection.color = Some(color.to_string());
-                            }
-                        }
-
                         collections.insert(collection);
                     }
 
@@ -167,4 +166,35 @@ impl ListCollections {
             }
         }
     }
+
+    fn next_state(&mut self) -> State {
+        match self.pending_paths.pop() {
+            Some(path) => {
+                let fs = ReadDir::new(&path);
+                State::ListCollectionEntries(path, fs)
+            }
+            None => {
+                let fs = ReadFiles::new(self.metadata_paths.drain());
+                State::ReadMetadataFiles(fs)
+            }
+        }
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    match path.file_name() {
+        Some(name) => name.as_encoded_bytes().starts_with(b"."),
+        None => true,
+    }
+}
+
+fn read_metadata(metadata: &mut HashMap<PathBuf, Vec<u8>>, path: &Path) -> Option<String> {
+    let contents = metadata.remove(path)?;
+    let contents = String::from_utf8_lossy(&contents);
+
+    if contents.trim().is_empty() {
+        None
+    } else {
+        Some(contents.to_string())
+    }
 }
@@ -0,0 +1,145 @@
+//! I/O-free coroutine to list item references in a Vdir collection.
+
+use std::{collections::HashSet, path::Path};
+
+use io_fs::{
+    coroutines::{read_dir::ReadDir, read_files::ReadFiles},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use thiserror::Error;
+
+use crate::{
+    etag::Etag,
+    item::{ItemFormat, ItemHref},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ListItemHrefsError {
+    /// An error occured during the directory listing.
+    #[error("List Vdir items error")]
+    ListDirsError(#[source] FsError),
+
+    /// An error occured during the items files reading.
+    #[error("Read Vdir items error")]
+    ListFilesError(#[source] FsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum ListItemHrefsResult {
+    /// The coroutine successfully terminated its progression.
+    Ok(HashSet<ItemHref>),
+
+    /// The coroutine encountered an error.
+    Err(ListItemHrefsError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListItems(ReadDir),
+    ReadItems(ReadFiles),
+}
+
+/// I/O-free coroutine to list item references in a Vdir collection.
+///
+/// Unlike [`crate::coroutines::list_items::ListItems`], items are
+/// never parsed: their format is guessed from their file extension,
+/// which makes this coroutine suitable for large collections.
+#[derive(Debug)]
+pub struct ListItemHrefs {
+    etags: bool,
+    state: State,
+}
+
+impl ListItemHrefs {
+    /// Creates a new coroutine from the given collection's path.
+    ///
+    /// Items are listed without their entity tag: only the
+    /// collection's directory is read. See
+    /// [`ListItemHrefs::with_etags`].
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let fs = ReadDir::new(path.as_ref());
+        let state = State::ListItems(fs);
+
+        Self {
+            etags: false,
+            state,
+        }
+    }
+
+    /// Lists items with their entity tag.
+    ///
+    /// Since io-fs does not expose file metadata (modification time,
+    /// inode), entity tags are computed from the raw contents of
+    /// items: every item file is read, without being parsed. This is
+    /// not a cheap change detection, and the entity tags are not
+    /// compatible with vdirsyncer ones (see [`Etag`]).
+    pub fn with_etags(mut self, etags: bool) -> Self {
+        self.etags = etags;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> ListItemHrefsResult {
+        loop {
+            match &mut self.state {
+                State::ListItems(fs) => {
+                    let mut item_paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break ListItemHrefsResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ListItemHrefsError::ListDirsError(err);
+                            break ListItemHrefsResult::Err(err);
+                        }
+                    };
+
+                    item_paths.retain(|path| ItemFormat::from_path(path).is_some());
+
+                    if self.etags {
+                        let fs = ReadFiles::new(item_paths);
+                        self.state = State::ReadItems(fs);
+                        continue;
+                    }
+
+                    let hrefs = item_paths
+                        .into_iter()
+                        .filter_map(|path| {
+                            let format = ItemFormat::from_path(&path)?;
+                            let etag = None;
+                            Some(ItemHref { path, format, etag })
+                        })
+                        .collect();
+
+                    break ListItemHrefsResult::Ok(hrefs);
+                }
+                State::ReadItems(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break ListItemHrefsResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ListItemHrefsError::ListFilesError(err);
+                            break ListItemHrefsResult::Err(err);
+                        }
+                    };
+
+                    let hrefs = contents
+                        .into_iter()
+                        .filter_map(|(path, contents)| {
+                            let format = ItemFormat::from_path(&path)?;
+                            let etag = Some(Etag::from_contents(contents));
+                            Some(ItemHref { path, format, etag })
+                        })
+                        .collect();
+
+                    break ListItemHrefsResult::Ok(hrefs);
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,21 @@
 //! I/O-free coroutine to list items in a Vdir collection.
 
-use std::{collections::HashSet, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
-use calcard::{icalendar::ICalendar, vcard::VCard};
 use io_fs::{
-    coroutines::{read_dir::ReadDir, read_files::ReadFiles},
+    coroutines::{read_dir::ReadDir, read_file::ReadFile, read_files::ReadFiles},
     error::{FsError, FsResult},
     io::FsIo,
 };
+use log::debug;
 use thiserror::Error;
 
 use crate::{
-    constants::{ICS, VCF},
-    item::Item,
-    item::ItemKind,
+    coroutines::read_item::{parse_item, ReadItemError},
+    item::{Item, ItemFormat},
 };
 
 /// Errors that can occur during the coroutine progression.
@@ -32,7 +34,10 @@ pub enum ListItemsError {
 #[derive(Clone, Debug)]
 pub enum ListItemsResult {
     /// The coroutine successfully terminated its progression.
-    Ok(HashSet<Item>),
+    ///
+    /// Contains the parsed items, as well as the parsing errors of
+    /// the items that could not be parsed, indexed by path.
+    Ok(HashSet<Item>, HashMap<PathBuf, ReadItemError>),
 
     /// The coroutine encountered an error.
     Err(ListItemsError),
@@ -46,12 +51,26 @@ pub enum ListItemsResult {
 enum State {
     ListItems(ReadDir),
     ReadItems(ReadFiles),
+    ReadItem(PathBuf, ReadFile),
 }
 
 /// I/O-free coroutine to list items in a Vdir collection.
+///
+/// Items are the collection's entries having either a vCard (.vcf)
+/// or an iCalendar (.ics) file extension.
+///
+/// Runtimes cannot tell the coroutine the type of an entry: when
+/// reading the items fails (for example because a directory is named
+/// like an item, with EISDIR), resume the coroutine with the
+/// unprocessed request ([`FsIo::ReadFiles`]). Items are then read one
+/// by one, and the entries that cannot be read are skipped the same
+/// way, by resuming the coroutine with the unprocessed
+/// [`FsIo::ReadFile`] request. See [`crate::runtime::HandBack`].
 #[derive(Debug)]
 pub struct ListItems {
     state: State,
+    pending_paths: Vec<PathBuf>,
+    contents: HashMap<PathBuf, Vec<u8>>,
 }
 
 impl ListItems {
@@ -60,7 +79,11 @@ impl ListItems {
         let fs = ReadDir::new(path.as_ref());
         let state = State::ListItems(fs);
 
-        Self { state }
+        Self {
+            state,
+            pending_paths: Vec::new(),
+            contents: HashMap::new(),
+        }
     }
 
     /// Makes the coroutine progress.
@@ -77,27 +100,29 @@ impl ListItems {
                         }
                     };
 
-                    item_paths.retain(|path| {
-                        if !path.is_file() {
-                            return false;
-                        }
-
-                        let Some(ext) = path.extension() else {
-                            return false;
-                        };
-
-                        if ext != VCF && ext != ICS {
-                            return false;
-                        }
-
-                        return true;
-                    });
+                    // NOTE: entries are filtered by extension only, so
+                    // that the coroutine does not need to access the
+                    // filesystem by itself
+                    item_paths.retain(|path| ItemFormat::from_path(path).is_some());
 
                     let fs = ReadFiles::new(item_paths);
                     self.state = State::ReadItems(fs);
                 }
                 State::ReadItems(fs) => {
-                    let contents = match fs.resume(arg.take()) {
+                    if let Some(FsIo::ReadFiles(Err(paths))) = &arg {
+                        debug!("cannot read Vdir items at once, read them one by one");
+                        self.pending_paths = paths.iter().cloned().collect();
+                        arg = None;
+
+                        match self.next_state() {
+                            Some(state) => self.state = state,
+                            None => break self.parse_items(),
+                        }
+
+                        continue;
+                    }
+
+                    self.contents = match fs.resume(arg.take()) {
                         FsResult::Ok(contents) => contents,
                         FsResult::Io(io) => break ListItemsResult::Io(io),
                         FsResult::Err(err) => {
@@ -106,45 +131,56 @@ impl ListItems {
                         }
                     };
 
-                    let mut items = HashSet::new();
-
-                    for (path, contents) in contents {
-                        let Some(ext) = path.extension() else {
-                            continue;
-                        };
-
-                        let Ok(contents) = String::from_utf8(contents) else {
-                            continue;
+                    break self.parse_items();
+                }
+                State::ReadItem(path, fs) => {
+                    if matches!(&arg, Some(FsIo::ReadFile(Err(failed))) if failed == path) {
+                        debug!("skip unreadable Vdir item at {}", path.display());
+                        arg = None;
+                    } else {
+                        let contents = match fs.resume(arg.take()) {
+                            FsResult::Ok(contents) => contents,
+                            FsResult::Io(io) => break ListItemsResult::Io(io),
+                            FsResult::Err(err) => {
+                                let err = ListItemsError::ListFilesError(err);
+                                break ListItemsResult::Err(err);
+                            }
                         };
 
-                        if ext == ICS {
-                            let Ok(ical) = ICalendar::parse(contents) else {
-                                continue;
-                            };
-
-                            items.insert(Item {
-                                path,
-                                kind: ItemKind::Ical(ical),
-                            });
+                        self.contents.insert(path.clone(), contents);
+                    }
 
-                            continue;
-                        }
+                    match self.next_state() {
+                        Some(state) => self.state = state,
+                        None => break self.parse_items(),
+                    }
+                }
+            }
+        }
+    }
 
-                        if ext == VCF {
-                            let Ok(vcard) = VCard::parse(contents) else {
-                                continue;
-                            };
+    fn next_state(&mut self) -> Option<State> {
+        let path = self.pending_paths.pop()?;
+        let fs = ReadFile::new(&path);
+        Some(State::ReadItem(path, fs))
+    }
 
-                            items.insert(Item {
-                                path,
-                                kind: ItemKind::Vcard(vcard),
-                            });
-                        }
-                    }
+    fn parse_items(&mut self) -> ListItemsResult {
+        let mut items = HashSet::new();
+        let mut errors = HashMap::new();
 
-                    break ListItemsResult::Ok(items);
+        for (path, contents) in self.contents.drain() {
+            match parse_item(path.clone(), contents) {
+                Ok(item) => {
+                    items.insert(item);
+                }
+                Err(err) => {
+                    debug!("cannot parse Vdir item at {}: {err}", path.display());
+                    errors.insert(path, err);
                 }
             }
         }
+
+        ListItemsResult::Ok(items, errors)
     }
 }
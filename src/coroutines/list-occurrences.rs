@@ -0,0 +1,282 @@
+//! I/O-free coroutine to list event and todo occurrences of a Vdir
+//! collection within a time range.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use calcard::{
+    common::timezone::Tz,
+    icalendar::{
+        dates::{CalendarExpand, TimeOrDelta},
+        ICalendar, ICalendarComponent, ICalendarComponentType, ICalendarProperty,
+    },
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use io_fs::io::FsIo;
+use log::debug;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        list_items::{ListItems, ListItemsError, ListItemsResult},
+        read_item::ReadItemError,
+    },
+    item::{Item, ItemKind},
+};
+
+/// The default maximum number of recurrence instances expanded per
+/// item.
+pub const DEFAULT_EXPANSION_LIMIT: usize = 1_000_000;
+
+/// The number of recurrence instances expanded per item at first.
+const INITIAL_EXPANSION_LIMIT: usize = 64;
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ListOccurrencesError {
+    /// An error occured during the items listing.
+    #[error("List Vdir items error")]
+    ListItemsError(#[source] ListItemsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum ListOccurrencesResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the occurrences overlapping the time range sorted by
+    /// start, the paths of the items whose recurrences could not be
+    /// expanded up to the range end (see
+    /// [`ListOccurrences::with_expansion_limit`]), as well as the
+    /// parsing errors of the items that could not be parsed, indexed
+    /// by path.
+    Ok(
+        Vec<Occurrence>,
+        HashSet<PathBuf>,
+        HashMap<PathBuf, ReadItemError>,
+    ),
+
+    /// The coroutine encountered an error.
+    Err(ListOccurrencesError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+/// An occurrence of an event or a todo.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Occurrence {
+    /// The path of the item the occurrence belongs to.
+    pub path: PathBuf,
+
+    /// The component the occurrence is generated from.
+    ///
+    /// For overridden occurrences, this is the component holding
+    /// the matching RECURRENCE-ID.
+    pub component: ICalendarComponent,
+
+    /// The start of the occurrence.
+    pub start: DateTime<Tz>,
+
+    /// The end of the occurrence.
+    pub end: DateTime<Tz>,
+}
+
+/// I/O-free coroutine to list event and todo occurrences of a Vdir
+/// collection within a time range.
+///
+/// Recurring components are expanded using their RRULE, RDATE and
+/// EXDATE properties, and occurrences overridden by a component
+/// with a matching RECURRENCE-ID are replaced by it. Dates are
+/// resolved using their TZID (or the default time zone for floating
+/// dates) before being compared to the range, following the CalDAV
+/// `time-range` semantics (RFC 4791 §9.9).
+///
+/// Recurrence expansion starts from the first instance, and stops
+/// once every recurring component reaches the range end.
+#[derive(Debug)]
+pub struct ListOccurrences {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    default_tz: Tz,
+    expansion_limit: usize,
+    list: ListItems,
+}
+
+impl ListOccurrences {
+    /// Creates a new coroutine from the given collection path and
+    /// time range.
+    ///
+    /// The range start is inclusive, the range end is exclusive.
+    pub fn new(path: impl AsRef<Path>, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            end,
+            default_tz: Tz::Fixed(FixedOffset::east_opt(0).unwrap()),
+            expansion_limit: DEFAULT_EXPANSION_LIMIT,
+            list: ListItems::new(path),
+        }
+    }
+
+    /// Changes the time zone of floating dates, UTC by default.
+    pub fn with_default_tz(mut self, tz: Tz) -> Self {
+        self.default_tz = tz;
+        self
+    }
+
+    /// Changes the maximum number of recurrence instances expanded
+    /// per item, [`DEFAULT_EXPANSION_LIMIT`] by default.
+    ///
+    /// Instances are counted from the first one, and shared by all
+    /// the recurring components of the item: an endless recurring
+    /// component consumes the whole limit before the following
+    /// ones. Items reaching the limit before the range end are
+    /// reported as truncated.
+    pub fn with_expansion_limit(mut self, limit: usize) -> Self {
+        self.expansion_limit = limit;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<FsIo>) -> ListOccurrencesResult {
+        match self.list.resume(arg) {
+            ListItemsResult::Ok(items, errors) => {
+                let mut occurrences = Vec::new();
+                let mut truncated = HashSet::new();
+
+                for item in items {
+                    let path = item.path.clone();
+                    let (item_occurrences, item_truncated) = self.occurrences(item);
+
+                    if item_truncated {
+                        debug!("truncated expansion of Vdir item at {}", path.display());
+                        truncated.insert(path);
+                    }
+
+                    occurrences.extend(item_occurrences);
+                }
+
+                occurrences.sort_by(|a, b| (a.start, &a.path).cmp(&(b.start, &b.path)));
+
+                ListOccurrencesResult::Ok(occurrences, truncated, errors)
+            }
+            ListItemsResult::Io(io) => ListOccurrencesResult::Io(io),
+            ListItemsResult::Err(err) => {
+                let err = ListOccurrencesError::ListItemsError(err);
+                ListOccurrencesResult::Err(err)
+            }
+        }
+    }
+
+    /// Expands the given item, and keeps the occurrences overlapping
+    /// the time range.
+    ///
+    /// Also returns `true` if the expansion has been truncated.
+    fn occurrences(&self, item: Item) -> (Vec<Occurrence>, bool) {
+        let ItemKind::Ical(ical) = &item.kind else {
+            return (Vec::new(), false);
+        };
+
+        let (expand, truncated) = self.expand(ical);
+
+        for err in expand.errors {
+            debug!(
+                "cannot expand component {} of Vdir item at {}: {:?}",
+                err.comp_id,
+                item.path.display(),
+                err.error,
+            );
+        }
+
+        let occurrences = expand
+            .events
+            .into_iter()
+            .filter_map(|event| {
+                let component = ical.components.get(event.comp_id as usize)?;
+
+                if !matches!(
+                    component.component_type,
+                    ICalendarComponentType::VEvent | ICalendarComponentType::VTodo
+                ) {
+                    return None;
+                }
+
+                let end = match event.end {
+                    TimeOrDelta::Time(end) => end,
+                    TimeOrDelta::Delta(delta) => event.start + delta,
+                };
+
+                if !self.overlaps(&event.start, &end) {
+                    return None;
+                }
+
+                Some(Occurrence {
+                    path: item.path.clone(),
+                    component: component.clone(),
+                    start: event.start,
+                    end,
+                })
+            })
+            .collect();
+
+        (occurrences, truncated)
+    }
+
+    /// Expands the recurrences of the given calendar up to the range
+    /// end.
+    ///
+    /// Since the expansion cannot start from the range start, the
+    /// number of expanded instances is doubled until every recurring
+    /// component reaches the range end, or until the expansion does
+    /// not grow anymore. Also returns `true` if the expansion limit
+    /// has been reached before.
+    fn expand(&self, ical: &ICalendar) -> (CalendarExpand, bool) {
+        let recurring: Vec<_> = ical
+            .components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| component.property(&ICalendarProperty::Rrule).is_some())
+            .map(|(id, _)| id as u32)
+            .collect();
+
+        let mut limit = INITIAL_EXPANSION_LIMIT.min(self.expansion_limit);
+        let mut expand = ical.expand_dates(self.default_tz, limit);
+
+        loop {
+            let reaches_end = |id: &u32| {
+                expand
+                    .events
+                    .iter()
+                    .any(|event| event.comp_id == *id && event.start >= self.end)
+            };
+
+            if recurring.iter().all(reaches_end) {
+                break (expand, false);
+            }
+
+            if limit >= self.expansion_limit {
+                break (expand, true);
+            }
+
+            limit = limit.saturating_mul(2).min(self.expansion_limit);
+            let next = ical.expand_dates(self.default_tz, limit);
+
+            if next.events.len() == expand.events.len() {
+                break (next, false);
+            }
+
+            expand = next;
+        }
+    }
+
+    fn overlaps(&self, start: &DateTime<Tz>, end: &DateTime<Tz>) -> bool {
+        if start == end {
+            *start >= self.start && *start < self.end
+        } else {
+            *start < self.end && *end > self.start
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! I/O-free coroutine to look up email addresses in Vdir
+//! collections.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use io_fs::io::FsIo;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        list_items::{ListItems, ListItemsError, ListItemsResult},
+        read_item::ReadItemError,
+    },
+    lookup::EmailContact,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum LookupEmailsError {
+    /// An error occured during the items listing of a collection.
+    #[error("List Vdir items of collection {1} error")]
+    ListItemsError(#[source] ListItemsError, PathBuf),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum LookupEmailsResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the matching email addresses without duplicates,
+    /// sorted by email then by name, as well as the parsing errors
+    /// of the items that could not be parsed, indexed by path.
+    Ok(Vec<EmailContact>, HashMap<PathBuf, ReadItemError>),
+
+    /// The coroutine encountered an error.
+    Err(LookupEmailsError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListItems(PathBuf, ListItems),
+    Done,
+}
+
+/// I/O-free coroutine to look up email addresses in Vdir
+/// collections.
+///
+/// vCards of the given collections are listed one collection after
+/// the other. See [`EmailContact::from_item`] for the matching
+/// rules, and [`crate::lookup::format_mutt`] to format the result.
+#[derive(Debug)]
+pub struct LookupEmails {
+    query: String,
+    collections: Vec<PathBuf>,
+    contacts: BTreeSet<EmailContact>,
+    errors: HashMap<PathBuf, ReadItemError>,
+    state: State,
+}
+
+impl LookupEmails {
+    /// Creates a new coroutine from the given collection paths and
+    /// query.
+    pub fn new(
+        collections: impl IntoIterator<Item = impl AsRef<Path>>,
+        query: impl ToString,
+    ) -> Self {
+        let mut collections: Vec<_> = collections
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+
+        // NOTE: collections are popped from the end
+        collections.reverse();
+
+        let mut lookup = Self {
+            query: query.to_string(),
+            collections,
+            contacts: BTreeSet::new(),
+            errors: HashMap::new(),
+            state: State::Done,
+        };
+
+        lookup.state = lookup.next_state();
+        lookup
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> LookupEmailsResult {
+        loop {
+            match &mut self.state {
+                State::ListItems(path, coroutine) => {
+                    let (items, errors) = match coroutine.resume(arg.take()) {
+                        ListItemsResult::Ok(items, errors) => (items, errors),
+                        ListItemsResult::Io(io) => break LookupEmailsResult::Io(io),
+                        ListItemsResult::Err(err) => {
+                            let err = LookupEmailsError::ListItemsError(err, path.clone());
+                            break LookupEmailsResult::Err(err);
+                        }
+                    };
+
+                    for item in items {
+                        let contacts = EmailContact::from_item(&item, &self.query);
+                        self.contacts.extend(contacts);
+                    }
+
+                    self.errors.extend(errors);
+                    self.state = self.next_state();
+                }
+                State::Done => {
+                    let contacts = mem::take(&mut self.contacts).into_iter().collect();
+                    let errors = mem::take(&mut self.errors);
+                    break LookupEmailsResult::Ok(contacts, errors);
+                }
+            }
+        }
+    }
+
+    fn next_state(&mut self) -> State {
+        match self.collections.pop() {
+            Some(path) => {
+                let coroutine = ListItems::new(&path);
+                State::ListItems(path, coroutine)
+            }
+            None => State::Done,
+        }
+    }
+}
@@ -0,0 +1,356 @@
+//! I/O-free coroutine to mirror a Vdir collection into another one.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use io_fs::{
+    coroutines::{create_files::CreateFiles, rename::Rename},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use log::{debug, warn};
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        delete_item::{DeleteItem, DeleteItemError, DeleteItemResult},
+        list_items::{ListItems, ListItemsError, ListItemsResult},
+        update_item::{UpdateItem, UpdateItemError, UpdateItemResult},
+    },
+    etag::Etag,
+    item::Item,
+    tmp::tmp_path,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum MirrorCollectionError {
+    /// An error occured during the source items listing.
+    #[error("List source Vdir items error")]
+    ListSourceItemsError(#[source] ListItemsError),
+
+    /// An error occured during the target items listing.
+    #[error("List target Vdir items error")]
+    ListTargetItemsError(#[source] ListItemsError),
+
+    /// An error occured during the creation of temporary target
+    /// items.
+    #[error("Create temporary target Vdir items error")]
+    CreateTempFilesError(#[source] FsError),
+
+    /// An error occured during the move of temporary target items to
+    /// their final path.
+    #[error("Save target Vdir items error")]
+    SaveFilesError(#[source] FsError),
+
+    /// An error occured during the update of a target item.
+    #[error("Update target Vdir item error")]
+    UpdateItemError(#[source] UpdateItemError),
+
+    /// An error occured during the deletion of a target item.
+    #[error("Delete target Vdir item error")]
+    DeleteItemError(#[source] DeleteItemError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum MirrorCollectionResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the changes applied on the target collection, or the
+    /// planned ones in dry-run mode.
+    Ok(Vec<MirrorChange>),
+
+    /// The coroutine encountered an error.
+    Err(MirrorCollectionError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+/// A change applied on the target collection.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MirrorChange {
+    /// The item has been created at the given href.
+    Create(String),
+
+    /// The item has been updated at the given href.
+    Update(String),
+
+    /// The item has been deleted at the given href.
+    Delete(String),
+}
+
+#[derive(Debug)]
+enum Operation {
+    Update(Item, Option<Etag>),
+    Delete(PathBuf, Option<Etag>),
+}
+
+#[derive(Debug)]
+enum State {
+    ListSourceItems(ListItems),
+    ListTargetItems(ListItems),
+    CreateTempItems(CreateFiles),
+    MoveTempItems(Rename),
+    UpdateItem(UpdateItem),
+    DeleteItem(DeleteItem),
+    Done,
+}
+
+/// I/O-free coroutine to mirror a Vdir collection into another one.
+///
+/// Items of the source collection are copied into the target
+/// collection when they are new or when their etag changed, and
+/// items of the target collection missing from the source collection
+/// are deleted. Items are matched by href (file name). Unlike
+/// [`crate::coroutines::sync_collection::SyncCollection`], no status
+/// is needed: the source collection always wins.
+///
+/// Source items that cannot be parsed are skipped, and their target
+/// counterpart is left untouched.
+///
+/// New items are written all at once, without listing the target
+/// collection again: they are first written into temporary files,
+/// then moved to their final path. Updates and deletions are applied
+/// item by item, so that their etag can be checked right before.
+#[derive(Debug)]
+pub struct MirrorCollection {
+    target: PathBuf,
+    dry_run: bool,
+    source_items: HashSet<Item>,
+    skipped_hrefs: HashSet<String>,
+    contents: HashMap<PathBuf, Vec<u8>>,
+    rename_paths: Vec<(PathBuf, PathBuf)>,
+    operations: VecDeque<Operation>,
+    changes: Vec<MirrorChange>,
+    state: State,
+}
+
+impl MirrorCollection {
+    /// Creates a new coroutine from the given source and target
+    /// collection paths.
+    pub fn new(source: impl Into<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        let state = State::ListSourceItems(ListItems::new(source.into()));
+
+        Self {
+            target: target.into(),
+            dry_run: false,
+            source_items: HashSet::new(),
+            skipped_hrefs: HashSet::new(),
+            contents: HashMap::new(),
+            rename_paths: Vec::new(),
+            operations: VecDeque::new(),
+            changes: Vec::new(),
+            state,
+        }
+    }
+
+    /// Enables or disables the dry-run mode.
+    ///
+    /// In dry-run mode, the target collection is left untouched and
+    /// the coroutine only reports the planned changes.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> MirrorCollectionResult {
+        loop {
+            match &mut self.state {
+                State::ListSourceItems(coroutine) => {
+                    let (items, errors) = match coroutine.resume(arg.take()) {
+                        ListItemsResult::Ok(items, errors) => (items, errors),
+                        ListItemsResult::Io(io) => break MirrorCollectionResult::Io(io),
+                        ListItemsResult::Err(err) => {
+                            let err = MirrorCollectionError::ListSourceItemsError(err);
+                            break MirrorCollectionResult::Err(err);
+                        }
+                    };
+
+                    for (path, err) in errors {
+                        warn!("skip invalid Vdir item at {}: {err}", path.display());
+                        self.skipped_hrefs.extend(href(&path));
+                    }
+
+                    self.source_items = items;
+
+                    let coroutine = ListItems::new(&self.target);
+                    self.state = State::ListTargetItems(coroutine);
+                }
+                State::ListTargetItems(coroutine) => {
+                    let (items, errors) = match coroutine.resume(arg.take()) {
+                        ListItemsResult::Ok(items, errors) => (items, errors),
+                        ListItemsResult::Io(io) => break MirrorCollectionResult::Io(io),
+                        ListItemsResult::Err(err) => {
+                            let err = MirrorCollectionError::ListTargetItemsError(err);
+                            break MirrorCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.plan(items, errors.into_keys());
+
+                    if self.dry_run {
+                        self.contents.clear();
+                        self.rename_paths.clear();
+                        self.operations.clear();
+                    }
+
+                    self.state = self.next_state();
+                }
+                State::CreateTempItems(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break MirrorCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = MirrorCollectionError::CreateTempFilesError(err);
+                            break MirrorCollectionResult::Err(err);
+                        }
+                    }
+
+                    self.state = self.next_state();
+                }
+                State::MoveTempItems(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break MirrorCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = MirrorCollectionError::SaveFilesError(err);
+                            break MirrorCollectionResult::Err(err);
+                        }
+                    }
+
+                    self.state = self.next_state();
+                }
+                State::UpdateItem(coroutine) => {
+                    match coroutine.resume(arg.take()) {
+                        UpdateItemResult::Ok => (),
+                        UpdateItemResult::Io(io) => break MirrorCollectionResult::Io(io),
+                        UpdateItemResult::Err(err) => {
+                            let err = MirrorCollectionError::UpdateItemError(err);
+                            break MirrorCollectionResult::Err(err);
+                        }
+                    }
+
+                    self.state = self.next_state();
+                }
+                State::DeleteItem(coroutine) => {
+                    match coroutine.resume(arg.take()) {
+                        DeleteItemResult::Ok => (),
+                        DeleteItemResult::Io(io) => break MirrorCollectionResult::Io(io),
+                        DeleteItemResult::Err(err) => {
+                            let err = MirrorCollectionError::DeleteItemError(err);
+                            break MirrorCollectionResult::Err(err);
+                        }
+                    }
+
+                    self.state = self.next_state();
+                }
+                State::Done => {
+                    break MirrorCollectionResult::Ok(mem::take(&mut self.changes));
+                }
+            }
+        }
+    }
+
+    /// Computes the operations to apply on the target collection.
+    fn plan(
+        &mut self,
+        target_items: HashSet<Item>,
+        invalid_paths: impl IntoIterator<Item = PathBuf>,
+    ) {
+        let mut target_items: HashMap<String, Item> = target_items
+            .into_iter()
+            .filter_map(|item| Some((href(&item.path)?, item)))
+            .collect();
+
+        let mut invalid_hrefs: HashSet<String> = invalid_paths
+            .into_iter()
+            .filter_map(|path| href(&path))
+            .collect();
+
+        for source_item in mem::take(&mut self.source_items) {
+            let Some(href) = href(&source_item.path) else {
+                continue;
+            };
+
+            let path = self.target.join(&href);
+
+            let item = Item {
+                path: path.clone(),
+                etag: None,
+                ..source_item.clone()
+            };
+
+            match target_items.remove(&href) {
+                Some(target_item) => {
+                    if target_item.etag == source_item.etag {
+                        continue;
+                    }
+
+                    self.push(
+                        MirrorChange::Update(href),
+                        Operation::Update(item, target_item.etag),
+                    );
+                }
+                None if invalid_hrefs.remove(&href) => {
+                    self.push(MirrorChange::Update(href), Operation::Update(item, None));
+                }
+                None => {
+                    debug!("plan {:?}", MirrorChange::Create(href.clone()));
+                    let path_tmp = tmp_path(&path);
+                    self.contents.insert(path_tmp.clone(), item.to_bytes());
+                    self.rename_paths.push((path_tmp, path));
+                    self.changes.push(MirrorChange::Create(href));
+                }
+            }
+        }
+
+        let extra_items = target_items
+            .into_iter()
+            .map(|(href, item)| (href, item.etag))
+            .chain(invalid_hrefs.into_iter().map(|href| (href, None)));
+
+        for (href, etag) in extra_items {
+            if self.skipped_hrefs.contains(&href) {
+                continue;
+            }
+
+            let path = self.target.join(&href);
+            self.push(MirrorChange::Delete(href), Operation::Delete(path, etag));
+        }
+    }
+
+    fn push(&mut self, change: MirrorChange, operation: Operation) {
+        debug!("plan {change:?}");
+        self.changes.push(change);
+        self.operations.push_back(operation);
+    }
+
+    fn next_state(&mut self) -> State {
+        if !self.contents.is_empty() {
+            let fs = CreateFiles::new(self.contents.drain());
+            return State::CreateTempItems(fs);
+        }
+
+        if !self.rename_paths.is_empty() {
+            let fs = Rename::new(self.rename_paths.drain(..));
+            return State::MoveTempItems(fs);
+        }
+
+        match self.operations.pop_front() {
+            Some(Operation::Update(item, etag)) => State::UpdateItem(UpdateItem::new(item, etag)),
+            Some(Operation::Delete(path, etag)) => State::DeleteItem(DeleteItem::new(path, etag)),
+            None => State::Done,
+        }
+    }
+}
+
+fn href(path: &Path) -> Option<String> {
+    Some(path.file_name()?.to_string_lossy().to_string())
+}
@@ -7,6 +7,8 @@
 //! [I/O]: crate::io
 //! [runtimes]: crate::runtimes
 
+#[path = "build-index.rs"]
+pub mod build_index;
 #[path = "create-collection.rs"]
 pub mod create_collection;
 #[path = "create-item.rs"]
@@ -15,13 +17,41 @@ pub mod create_item;
 pub mod delete_collection;
 #[path = "delete-item.rs"]
 pub mod delete_item;
+#[path = "find-item-by-uid.rs"]
+pub mod find_item_by_uid;
 #[path = "list-collections.rs"]
 pub mod list_collections;
+#[path = "list-item-hrefs.rs"]
+pub mod list_item_hrefs;
 #[path = "list-items.rs"]
 pub mod list_items;
+#[path = "list-occurrences.rs"]
+pub mod list_occurrences;
+#[path = "lookup-emails.rs"]
+pub mod lookup_emails;
+#[path = "mirror-collection.rs"]
+pub mod mirror_collection;
+#[path = "query-index.rs"]
+pub mod query_index;
+#[path = "query-items.rs"]
+pub mod query_items;
+#[path = "read-index.rs"]
+pub mod read_index;
 #[path = "read-item.rs"]
 pub mod read_item;
+#[path = "read-sync-status.rs"]
+pub mod read_sync_status;
+#[path = "refresh-index.rs"]
+pub mod refresh_index;
+#[path = "remove-tmp-files.rs"]
+pub mod remove_tmp_files;
+#[path = "search-items.rs"]
+pub mod search_items;
+#[path = "sync-collection.rs"]
+pub mod sync_collection;
 #[path = "update-collection.rs"]
 pub mod update_collection;
 #[path = "update-item.rs"]
 pub mod update_item;
+#[path = "update-sync-status.rs"]
+pub mod update_sync_status;
@@ -0,0 +1,81 @@
+//! I/O-free coroutine to query a Vdir collection index.
+
+use std::path::Path;
+
+use io_fs::io::FsIo;
+use thiserror::Error;
+
+use crate::{
+    coroutines::read_index::{ReadIndex, ReadIndexError, ReadIndexResult},
+    index::IndexEntry,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum QueryIndexError {
+    /// An error occured during the index reading.
+    #[error("Read Vdir index error")]
+    ReadIndexError(#[source] ReadIndexError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum QueryIndexResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the matching entries with their href, sorted by
+    /// name then by href.
+    Ok(Vec<(String, IndexEntry)>),
+
+    /// The coroutine encountered an error.
+    Err(QueryIndexError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+/// I/O-free coroutine to query a Vdir collection index.
+///
+/// Only the index file is read, items are neither read nor parsed.
+/// The index may therefore be outdated, see
+/// [`crate::coroutines::refresh_index::RefreshIndex`]. Collections
+/// without index give no entry.
+///
+/// See [`crate::index::Index::search`] for the matching rules.
+#[derive(Debug)]
+pub struct QueryIndex {
+    query: String,
+    read: ReadIndex,
+}
+
+impl QueryIndex {
+    /// Creates a new coroutine from the given collection path and
+    /// query.
+    pub fn new(path: impl AsRef<Path>, query: impl ToString) -> Self {
+        Self {
+            query: query.to_string(),
+            read: ReadIndex::new(path),
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<FsIo>) -> QueryIndexResult {
+        match self.read.resume(arg) {
+            ReadIndexResult::Ok(index) => {
+                let entries = index
+                    .search(&self.query)
+                    .into_iter()
+                    .map(|(href, entry)| (href.clone(), entry.clone()))
+                    .collect();
+
+                QueryIndexResult::Ok(entries)
+            }
+            ReadIndexResult::Io(io) => QueryIndexResult::Io(io),
+            ReadIndexResult::Err(err) => {
+                let err = QueryIndexError::ReadIndexError(err);
+                QueryIndexResult::Err(err)
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! I/O-free coroutine to query items of a Vdir collection.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use io_fs::io::FsIo;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        list_items::{ListItems, ListItemsError, ListItemsResult},
+        read_item::ReadItemError,
+    },
+    item::Item,
+    query::Filter,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum QueryItemsError {
+    /// An error occured during the items listing.
+    #[error("List Vdir items error")]
+    ListItemsError(#[source] ListItemsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum QueryItemsResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the items matching the filter, as well as the
+    /// parsing errors of the items that could not be parsed, indexed
+    /// by path.
+    Ok(HashSet<Item>, HashMap<PathBuf, ReadItemError>),
+
+    /// The coroutine encountered an error.
+    Err(QueryItemsError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+/// I/O-free coroutine to query items of a Vdir collection.
+///
+/// Items are listed then matched against the given [`Filter`], see
+/// [`crate::query`] for the filter expression syntax.
+#[derive(Debug)]
+pub struct QueryItems {
+    filter: Filter,
+    list: ListItems,
+}
+
+impl QueryItems {
+    /// Creates a new coroutine from the given collection path and
+    /// filter.
+    pub fn new(path: impl AsRef<Path>, filter: Filter) -> Self {
+        Self {
+            filter,
+            list: ListItems::new(path),
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, arg: Option<FsIo>) -> QueryItemsResult {
+        match self.list.resume(arg) {
+            ListItemsResult::Ok(mut items, errors) => {
+                items.retain(|item| self.filter.matches(&item.kind));
+                QueryItemsResult::Ok(items, errors)
+            }
+            ListItemsResult::Io(io) => QueryItemsResult::Io(io),
+            ListItemsResult::Err(err) => {
+                let err = QueryItemsError::ListItemsError(err);
+                QueryItemsResult::Err(err)
+            }
+        }
+    }
+}
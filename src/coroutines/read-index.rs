@@ -0,0 +1,119 @@
+//! I/O-free coroutine to read a Vdir collection index.
+
+use std::path::{Path, PathBuf};
+
+use io_fs::{
+    coroutines::{read_dir::ReadDir, read_file::ReadFile},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use thiserror::Error;
+
+use crate::{
+    constants::INDEX,
+    index::{Index, IndexError},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadIndexError {
+    /// An error occured during the collection directory listing.
+    #[error("List Vdir collection error")]
+    ReadDirError(#[source] FsError),
+
+    /// An error occured during the index file reading.
+    #[error("Read Vdir index file error")]
+    ReadFileError(#[source] FsError),
+
+    /// The index file contents could not be decoded.
+    #[error("Decode Vdir index at {1} error")]
+    DecodeError(#[source] IndexError, PathBuf),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum ReadIndexResult {
+    /// The coroutine successfully terminated its progression.
+    Ok(Index),
+
+    /// The coroutine encountered an error.
+    Err(ReadIndexError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListIndex(ReadDir),
+    ReadIndex(ReadFile),
+}
+
+/// I/O-free coroutine to read a Vdir collection index.
+///
+/// An empty index is returned when the collection does not have an
+/// index yet.
+///
+/// See [`Index::from_bytes`] for the index file format.
+#[derive(Debug)]
+pub struct ReadIndex {
+    path: PathBuf,
+    state: State,
+}
+
+impl ReadIndex {
+    /// Creates a new coroutine from the given collection path.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let fs = ReadDir::new(path.as_ref());
+        let state = State::ListIndex(fs);
+
+        Self {
+            path: path.as_ref().join(INDEX),
+            state,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> ReadIndexResult {
+        loop {
+            match &mut self.state {
+                State::ListIndex(fs) => {
+                    let paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break ReadIndexResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ReadIndexError::ReadDirError(err);
+                            break ReadIndexResult::Err(err);
+                        }
+                    };
+
+                    if !paths.contains(&self.path) {
+                        break ReadIndexResult::Ok(Index::default());
+                    }
+
+                    let fs = ReadFile::new(&self.path);
+                    self.state = State::ReadIndex(fs);
+                }
+                State::ReadIndex(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break ReadIndexResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ReadIndexError::ReadFileError(err);
+                            break ReadIndexResult::Err(err);
+                        }
+                    };
+
+                    break match Index::from_bytes(contents) {
+                        Ok(index) => ReadIndexResult::Ok(index),
+                        Err(err) => {
+                            let err = ReadIndexError::DecodeError(err, self.path.clone());
+                            ReadIndexResult::Err(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
@@ -11,8 +11,8 @@ use io_fs::{
 use thiserror::Error;
 
 use crate::{
-    constants::{ICS, VCF},
-    item::{Item, ItemKind},
+    etag::Etag,
+    item::{Item, ItemFormat, ItemKind},
 };
 
 /// Errors that can occur during the coroutine progression.
@@ -75,11 +75,10 @@ impl ReadItem {
 
     /// Makes the coroutine progress.
     pub fn resume(&mut self, arg: Option<FsIo>) -> ReadItemResult {
-        let p = self.path.clone();
-
-        let Some(ext) = self.path.extension() else {
-            return ReadItemResult::Err(ReadItemError::MissingExt(p));
-        };
+        if self.path.extension().is_none() {
+            let err = ReadItemError::MissingExt(self.path.clone());
+            return ReadItemResult::Err(err);
+        }
 
         let contents = match self.fs.resume(arg) {
             FsResult::Ok(paths) => paths,
@@ -87,48 +86,63 @@ impl ReadItem {
             FsResult::Io(io) => return ReadItemResult::Io(io),
         };
 
-        let Ok(contents) = String::from_utf8(contents) else {
-            return ReadItemResult::Err(ReadItemError::InvalidContents(p));
-        };
-
-        if ext == VCF {
-            let vcard = match VCard::parse(contents) {
-                Ok(vcard) => vcard,
-                Err(err) => {
-                    // NOTE: err is not a regular error
-                    // TODO: make better mapping
-                    let err = ReadItemError::InvalidVcardContents(format!("{err:?}"), p);
-                    return ReadItemResult::Err(err);
-                }
-            };
-
-            let item = Item {
-                path: mem::take(&mut self.path),
-                kind: ItemKind::Vcard(vcard),
-            };
-
-            return ReadItemResult::Ok(item);
+        match parse_item(mem::take(&mut self.path), contents) {
+            Ok(item) => ReadItemResult::Ok(item),
+            Err(err) => ReadItemResult::Err(err),
         }
+    }
+}
 
-        if ext == ICS {
-            let ical = match ICalendar::parse(contents) {
-                Ok(ical) => ical,
-                Err(err) => {
-                    // NOTE: err is not a regular error
-                    // TODO: make better mapping
-                    let err = ReadItemError::InvalidIcalContents(format!("{err:?}"), p);
-                    return ReadItemResult::Err(err);
-                }
-            };
-
-            let item = Item {
-                path: mem::take(&mut self.path),
-                kind: ItemKind::Ical(ical),
-            };
-
-            return ReadItemResult::Ok(item);
-        }
+/// Parses the given raw contents into a Vdir item, based on the
+/// given path's file extension.
+pub(crate) fn parse_item(path: PathBuf, contents: Vec<u8>) -> Result<Item, ReadItemError> {
+    if path.extension().is_none() {
+        return Err(ReadItemError::MissingExt(path));
+    }
+
+    let Some(format) = ItemFormat::from_path(&path) else {
+        return Err(ReadItemError::InvalidExt(path));
+    };
+
+    let etag = Some(Etag::from_contents(&contents));
+
+    let Ok(str_contents) = std::str::from_utf8(&contents) else {
+        return Err(ReadItemError::InvalidContents(path));
+    };
+
+    if format == ItemFormat::Vcard {
+        let vcard = match VCard::parse(str_contents) {
+            Ok(vcard) => vcard,
+            Err(err) => {
+                // NOTE: err is not a regular error
+                // TODO: make better mapping
+                let err = ReadItemError::InvalidVcardContents(format!("{err:?}"), path);
+                return Err(err);
+            }
+        };
 
-        ReadItemResult::Err(ReadItemError::InvalidExt(p))
+        return Ok(Item {
+            path,
+            kind: ItemKind::Vcard(vcard),
+            etag,
+            raw: Some(contents),
+        });
     }
+
+    let ical = match ICalendar::parse(str_contents) {
+        Ok(ical) => ical,
+        Err(err) => {
+            // NOTE: err is not a regular error
+            // TODO: make better mapping
+            let err = ReadItemError::InvalidIcalContents(format!("{err:?}"), path);
+            return Err(err);
+        }
+    };
+
+    Ok(Item {
+        path,
+        kind: ItemKind::Ical(ical),
+        etag,
+        raw: Some(contents),
+    })
 }
@@ -0,0 +1,143 @@
+//! I/O-free coroutine to read a Vdir synchronization status.
+
+use std::path::{Path, PathBuf};
+
+use io_fs::{
+    coroutines::{read_dir::ReadDir, read_file::ReadFile},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use thiserror::Error;
+
+use crate::sync::{SyncStatus, SyncStatusError};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum ReadSyncStatusError {
+    /// An error occured during the status directory listing.
+    #[error("List Vdir sync status directory error")]
+    ReadDirError(#[source] FsError),
+
+    /// An error occured during the status file reading.
+    #[error("Read Vdir sync status file error")]
+    ReadFileError(#[source] FsError),
+
+    /// The status file contents could not be decoded.
+    #[error("Decode Vdir sync status at {1} error")]
+    DecodeError(#[source] SyncStatusError, PathBuf),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum ReadSyncStatusResult {
+    /// The coroutine successfully terminated its progression.
+    Ok(SyncStatus),
+
+    /// The coroutine encountered an error.
+    Err(ReadSyncStatusError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum Format {
+    Native,
+    VdirsyncerJson,
+}
+
+#[derive(Debug)]
+enum State {
+    ListStatus(ReadDir),
+    ReadStatus(ReadFile),
+}
+
+/// I/O-free coroutine to read a Vdir synchronization status.
+///
+/// An empty status is returned when the status file does not exist
+/// yet, which is the case before the first synchronization.
+///
+/// See [`SyncStatus::from_bytes`] for the status file format.
+#[derive(Debug)]
+pub struct ReadSyncStatus {
+    path: PathBuf,
+    format: Format,
+    state: State,
+}
+
+impl ReadSyncStatus {
+    /// Creates a new coroutine from the given status file path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::with_format(path.into(), Format::Native)
+    }
+
+    /// Creates a new coroutine from the given legacy vdirsyncer JSON
+    /// status file path.
+    ///
+    /// Only the JSON status files written by vdirsyncer before 0.16
+    /// are supported, not the SQLite ones of later versions. See
+    /// [`SyncStatus::from_vdirsyncer_json`].
+    pub fn vdirsyncer_json(path: impl Into<PathBuf>) -> Self {
+        Self::with_format(path.into(), Format::VdirsyncerJson)
+    }
+
+    fn with_format(path: PathBuf, format: Format) -> Self {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let state = State::ListStatus(ReadDir::new(dir));
+
+        Self {
+            path,
+            format,
+            state,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> ReadSyncStatusResult {
+        loop {
+            match &mut self.state {
+                State::ListStatus(fs) => {
+                    let paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break ReadSyncStatusResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ReadSyncStatusError::ReadDirError(err);
+                            break ReadSyncStatusResult::Err(err);
+                        }
+                    };
+
+                    if !paths.contains(&self.path) {
+                        break ReadSyncStatusResult::Ok(SyncStatus::default());
+                    }
+
+                    let fs = ReadFile::new(&self.path);
+                    self.state = State::ReadStatus(fs);
+                }
+                State::ReadStatus(fs) => {
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break ReadSyncStatusResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = ReadSyncStatusError::ReadFileError(err);
+                            break ReadSyncStatusResult::Err(err);
+                        }
+                    };
+
+                    let status = match self.format {
+                        Format::Native => SyncStatus::from_bytes(contents),
+                        Format::VdirsyncerJson => SyncStatus::from_vdirsyncer_json(contents),
+                    };
+
+                    break match status {
+                        Ok(status) => ReadSyncStatusResult::Ok(status),
+                        Err(err) => {
+                            let err = ReadSyncStatusError::DecodeError(err, self.path.clone());
+                            ReadSyncStatusResult::Err(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
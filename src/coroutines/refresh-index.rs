@@ -0,0 +1,113 @@
+//! I/O-free coroutine to refresh a Vdir collection index.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use io_fs::io::FsIo;
+use log::warn;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        build_index::{BuildIndex, BuildIndexError, BuildIndexResult},
+        read_index::{ReadIndex, ReadIndexError, ReadIndexResult},
+        read_item::ReadItemError,
+    },
+    index::Index,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum RefreshIndexError {
+    /// An error occured during the previous index reading.
+    #[error("Read previous Vdir index error")]
+    ReadIndexError(#[source] ReadIndexError),
+
+    /// An error occured during the index building.
+    #[error("Build Vdir index error")]
+    BuildIndexError(#[source] BuildIndexError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+///
+/// See [`BuildIndexResult`].
+#[derive(Clone, Debug)]
+pub enum RefreshIndexResult {
+    /// The coroutine successfully terminated its progression.
+    Ok(Index, HashMap<PathBuf, ReadItemError>),
+
+    /// The coroutine encountered an error.
+    Err(RefreshIndexError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ReadIndex(ReadIndex),
+    BuildIndex(BuildIndex),
+}
+
+/// I/O-free coroutine to refresh a Vdir collection index.
+///
+/// The previous index is read, then only the items whose etag
+/// changed are parsed again, see [`BuildIndex::from_index`]. The
+/// index is built from scratch when the collection does not have
+/// one yet, or when it cannot be decoded (for example after a
+/// format version change).
+#[derive(Debug)]
+pub struct RefreshIndex {
+    path: PathBuf,
+    state: State,
+}
+
+impl RefreshIndex {
+    /// Creates a new coroutine from the given collection path.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let state = State::ReadIndex(ReadIndex::new(&path));
+
+        Self { path, state }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> RefreshIndexResult {
+        loop {
+            match &mut self.state {
+                State::ReadIndex(coroutine) => {
+                    let index = match coroutine.resume(arg.take()) {
+                        ReadIndexResult::Ok(index) => index,
+                        ReadIndexResult::Io(io) => break RefreshIndexResult::Io(io),
+                        ReadIndexResult::Err(ReadIndexError::DecodeError(err, path)) => {
+                            warn!("rebuild invalid Vdir index at {}: {err}", path.display());
+                            Index::default()
+                        }
+                        ReadIndexResult::Err(err) => {
+                            let err = RefreshIndexError::ReadIndexError(err);
+                            break RefreshIndexResult::Err(err);
+                        }
+                    };
+
+                    let coroutine = BuildIndex::from_index(&self.path, index);
+                    self.state = State::BuildIndex(coroutine);
+                }
+                State::BuildIndex(coroutine) => {
+                    break match coroutine.resume(arg.take()) {
+                        BuildIndexResult::Ok(index, errors) => {
+                            RefreshIndexResult::Ok(index, errors)
+                        }
+                        BuildIndexResult::Io(io) => RefreshIndexResult::Io(io),
+                        BuildIndexResult::Err(err) => {
+                            let err = RefreshIndexError::BuildIndexError(err);
+                            RefreshIndexResult::Err(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
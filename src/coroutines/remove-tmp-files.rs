@@ -0,0 +1,109 @@
+//! I/O-free coroutine to remove temporary files from a Vdir
+//! collection.
+
+use std::{collections::HashSet, mem, path::PathBuf};
+
+use io_fs::{
+    coroutines::{read_dir::ReadDir, remove_files::RemoveFiles},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use thiserror::Error;
+
+use crate::tmp::is_tmp_path;
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum RemoveTmpFilesError {
+    /// An error occured during the directory listing.
+    #[error("List Vdir collection entries error")]
+    ReadDirError(#[source] FsError),
+
+    /// An error occured during the temporary files deletion.
+    #[error("Remove Vdir temporary files error")]
+    RemoveFilesError(#[source] FsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum RemoveTmpFilesResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the paths of the removed temporary files.
+    Ok(HashSet<PathBuf>),
+
+    /// The coroutine encountered an error.
+    Err(RemoveTmpFilesError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListEntries(ReadDir),
+    RemoveTmpFiles(HashSet<PathBuf>, RemoveFiles),
+}
+
+/// I/O-free coroutine to remove temporary files from a Vdir
+/// collection.
+///
+/// Temporary files are left over by writers that crashed between
+/// the temporary file creation and its move to the final path. Since
+/// the coroutine cannot tell apart stale temporary files from the
+/// ones of a write in progress, it should be run when no other
+/// writer is operating on the collection.
+///
+/// See [`crate::tmp`].
+#[derive(Debug)]
+pub struct RemoveTmpFiles {
+    state: State,
+}
+
+impl RemoveTmpFiles {
+    /// Creates a new coroutine from the given collection's path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let fs = ReadDir::new(path);
+        let state = State::ListEntries(fs);
+
+        Self { state }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> RemoveTmpFilesResult {
+        loop {
+            match &mut self.state {
+                State::ListEntries(fs) => {
+                    let mut paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break RemoveTmpFilesResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = RemoveTmpFilesError::ReadDirError(err);
+                            break RemoveTmpFilesResult::Err(err);
+                        }
+                    };
+
+                    paths.retain(|path| is_tmp_path(path));
+
+                    if paths.is_empty() {
+                        break RemoveTmpFilesResult::Ok(paths);
+                    }
+
+                    let fs = RemoveFiles::new(paths.clone());
+                    self.state = State::RemoveTmpFiles(paths, fs);
+                }
+                State::RemoveTmpFiles(paths, fs) => {
+                    break match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => RemoveTmpFilesResult::Ok(mem::take(paths)),
+                        FsResult::Io(io) => RemoveTmpFilesResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = RemoveTmpFilesError::RemoveFilesError(err);
+                            RemoveTmpFilesResult::Err(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! I/O-free coroutine to search items across the collections of a
+//! Vdir root.
+
+use std::{
+    collections::HashMap,
+    mem,
+    path::{Path, PathBuf},
+};
+
+use io_fs::io::FsIo;
+use thiserror::Error;
+
+use crate::{
+    coroutines::{
+        list_collections::{ListCollections, ListCollectionsError, ListCollectionsResult},
+        list_items::{ListItems, ListItemsError, ListItemsResult},
+        read_item::ReadItemError,
+    },
+    search::{SearchHit, SearchQuery},
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum SearchItemsError {
+    /// An error occured during the collections listing.
+    #[error("List Vdir collections error")]
+    ListCollectionsError(#[source] ListCollectionsError),
+
+    /// An error occured during the items listing of a collection.
+    #[error("List Vdir items of collection {1} error")]
+    ListItemsError(#[source] ListItemsError, PathBuf),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum SearchItemsResult {
+    /// The coroutine successfully terminated its progression.
+    ///
+    /// Contains the matching items sorted by decreasing score then
+    /// by path, as well as the parsing errors of the items that
+    /// could not be parsed, indexed by path.
+    Ok(Vec<SearchHit>, HashMap<PathBuf, ReadItemError>),
+
+    /// The coroutine encountered an error.
+    Err(SearchItemsError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListCollections(ListCollections),
+    ListItems(PathBuf, ListItems),
+    Done,
+}
+
+/// I/O-free coroutine to search items across the collections of a
+/// Vdir root.
+///
+/// Collections are listed, then their items are listed and searched
+/// one collection after the other. See [`crate::search`] for the
+/// matching and ranking rules. An empty query gives no hit.
+#[derive(Debug)]
+pub struct SearchItems {
+    query: SearchQuery,
+    collections: Vec<PathBuf>,
+    hits: Vec<SearchHit>,
+    errors: HashMap<PathBuf, ReadItemError>,
+    state: State,
+}
+
+impl SearchItems {
+    /// Creates a new coroutine from the given root path and query.
+    pub fn new(root: impl AsRef<Path>, query: &str) -> Self {
+        let state = State::ListCollections(ListCollections::new(root));
+
+        Self {
+            query: SearchQuery::new(query),
+            collections: Vec::new(),
+            hits: Vec::new(),
+            errors: HashMap::new(),
+            state,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> SearchItemsResult {
+        loop {
+            match &mut self.state {
+                State::ListCollections(coroutine) => {
+                    let collections = match coroutine.resume(arg.take()) {
+                        ListCollectionsResult::Ok(collections) => collections,
+                        ListCollectionsResult::Io(io) => break SearchItemsResult::Io(io),
+                        ListCollectionsResult::Err(err) => {
+                            let err = SearchItemsError::ListCollectionsError(err);
+                            break SearchItemsResult::Err(err);
+                        }
+                    };
+
+                    if self.query.is_empty() {
+                        self.state = State::Done;
+                        continue;
+                    }
+
+                    self.collections = collections.into_iter().map(|c| c.path).collect();
+                    self.collections.sort();
+                    self.state = self.next_state();
+                }
+                State::ListItems(path, coroutine) => {
+                    let (items, errors) = match coroutine.resume(arg.take()) {
+                        ListItemsResult::Ok(items, errors) => (items, errors),
+                        ListItemsResult::Io(io) => break SearchItemsResult::Io(io),
+                        ListItemsResult::Err(err) => {
+                            let err = SearchItemsError::ListItemsError(err, path.clone());
+                            break SearchItemsResult::Err(err);
+                        }
+                    };
+
+                    let hits = items.into_iter().filter_map(|item| self.query.search(item));
+                    self.hits.extend(hits);
+                    self.errors.extend(errors);
+                    self.state = self.next_state();
+                }
+                State::Done => {
+                    let mut hits = mem::take(&mut self.hits);
+                    hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.item.path.cmp(&b.item.path)));
+
+                    let errors = mem::take(&mut self.errors);
+                    break SearchItemsResult::Ok(hits, errors);
+                }
+            }
+        }
+    }
+
+    fn next_state(&mut self) -> State {
+        match self.collections.pop() {
+            Some(path) => {
+                let coroutine = ListItems::new(&path);
+                State::ListItems(path, coroutine)
+            }
+            None => State::Done,
+        }
+    }
+}
@@ -0,0 +1,870 @@
+//! I/O-free coroutine to synchronize two Vdir collections.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    mem,
+    path::PathBuf,
+};
+
+use io_fs::{
+    coroutines::{
+        create_files::CreateFiles, read_file::ReadFile, read_files::ReadFiles, rename::Rename,
+    },
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use log::{debug, warn};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    conflict::{ConflictResolution, ConflictStrategy},
+    coroutines::{
+        delete_item::{DeleteItem, DeleteItemError, DeleteItemResult},
+        list_item_hrefs::{ListItemHrefs, ListItemHrefsError, ListItemHrefsResult},
+        read_item::parse_item,
+        update_item::{UpdateItem, UpdateItemError, UpdateItemResult},
+    },
+    etag::Etag,
+    item::{Item, ItemKind},
+    sync::{
+        SyncChange, SyncConflict, SyncReport, SyncSide, SyncStatus, SyncStatusHref, SyncStatusItem,
+    },
+    tmp::tmp_path,
+};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum SyncCollectionError {
+    /// An error occured during the items listing of one side.
+    #[error("List Vdir items of side {0:?} error")]
+    ListItemsError(SyncSide, #[source] ListItemHrefsError),
+
+    /// An error occured during the reading of items.
+    #[error("Read Vdir items error")]
+    ReadItemsError(#[source] FsError),
+
+    /// Multiple items share the same identifier on one side.
+    #[error("Multiple Vdir items of side {0:?} share the identifier {1}")]
+    DuplicateIdent(SyncSide, String),
+
+    /// An error occured during the creation of temporary items.
+    #[error("Create temporary Vdir items error")]
+    CreateTempFilesError(#[source] FsError),
+
+    /// An error occured during the move of temporary items to their
+    /// final path.
+    #[error("Save Vdir items error")]
+    SaveFilesError(#[source] FsError),
+
+    /// An error occured during the update of an item.
+    #[error("Update Vdir item error")]
+    UpdateItemError(#[source] UpdateItemError),
+
+    /// An error occured during the deletion of an item.
+    #[error("Delete Vdir item error")]
+    DeleteItemError(#[source] DeleteItemError),
+
+    /// An error occured during the reading of an item modified in
+    /// the meantime.
+    #[error("Read modified Vdir item error")]
+    ReadModifiedItemError(#[source] FsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum SyncCollectionResult {
+    /// The coroutine successfully terminated its progression.
+    Ok(SyncReport),
+
+    /// The coroutine encountered an error.
+    Err(SyncCollectionError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    ListItems(SyncSide, ListItemHrefs),
+    ReadItems(ReadFiles),
+    CreateTempFiles(CreateFiles),
+    MoveTempFiles(Rename),
+    UpdateItem(UpdateItem),
+    DeleteItem(DeleteItem),
+    ReadModifiedItem(PathBuf, ReadFile),
+    Done,
+}
+
+/// A change applied item by item, after checking the current etag of
+/// the item.
+#[derive(Debug)]
+struct Write {
+    ident: String,
+    change: SyncChange,
+    /// The item of the other side, reported as part of the conflict
+    /// when the item has been modified in the meantime.
+    other: Option<Item>,
+}
+
+#[derive(Debug)]
+enum Operation {
+    Update(Write, Item, Etag),
+    Delete(Write, PathBuf, Etag),
+}
+
+/// An item of one side, identified by its identifier.
+#[derive(Debug)]
+struct SideItem {
+    href: String,
+    etag: Etag,
+    /// The parsed item and its raw contents, only available for
+    /// items that changed since the last synchronization.
+    contents: Option<(Item, Vec<u8>)>,
+}
+
+/// I/O-free coroutine to synchronize two Vdir collections.
+///
+/// The synchronization algorithm is inspired by vdirsyncer: items
+/// are identified by their UID, and compared to the given
+/// [`SyncStatus`] using their etag. Creations, updates and deletions
+/// are propagated in both directions. Items modified on both sides
+/// are resolved using the [`ConflictStrategy`]: by default, they are
+/// reported as conflicts and left untouched.
+///
+/// Items are copied byte for byte, so that both sides end up with
+/// the exact same contents.
+///
+/// New items are written all at once: they are first written into
+/// temporary files, then moved to their final path. Updates and
+/// deletions are applied item by item, so that their etag can be
+/// checked right before (see [`UpdateItem`] and [`DeleteItem`]). An
+/// item modified in the meantime is left untouched, its previous
+/// status is kept and it is reported as a conflict.
+#[derive(Debug)]
+pub struct SyncCollection {
+    a: PathBuf,
+    b: PathBuf,
+    status: SyncStatus,
+    strategy: ConflictStrategy,
+    hrefs_a: HashMap<String, Etag>,
+    hrefs_b: HashMap<String, Etag>,
+    item_paths: HashMap<PathBuf, (SyncSide, String)>,
+    report: SyncReport,
+    contents: HashMap<PathBuf, Vec<u8>>,
+    rename_paths: Vec<(PathBuf, PathBuf)>,
+    operations: VecDeque<Operation>,
+    prev_items: HashMap<String, Option<SyncStatusItem>>,
+    write: Option<Write>,
+    state: State,
+}
+
+impl SyncCollection {
+    /// Creates a new coroutine from the given collection paths and
+    /// the status of the last synchronization.
+    ///
+    /// An empty status should be given for the first
+    /// synchronization.
+    pub fn new(a: impl Into<PathBuf>, b: impl Into<PathBuf>, status: SyncStatus) -> Self {
+        let a = a.into();
+        let b = b.into();
+        let state = State::ListItems(SyncSide::A, ListItemHrefs::new(&a));
+
+        Self {
+            a,
+            b,
+            status,
+            strategy: ConflictStrategy::default(),
+            hrefs_a: HashMap::new(),
+            hrefs_b: HashMap::new(),
+            item_paths: HashMap::new(),
+            report: SyncReport::default(),
+            contents: HashMap::new(),
+            rename_paths: Vec::new(),
+            operations: VecDeque::new(),
+            prev_items: HashMap::new(),
+            write: None,
+            state,
+        }
+    }
+
+    /// Sets the strategy used to resolve conflicts.
+    pub fn with_conflict_strategy(mut self, strategy: ConflictStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> SyncCollectionResult {
+        loop {
+            match &mut self.state {
+                State::ListItems(side, coroutine) => {
+                    let side = *side;
+
+                    let hrefs = match coroutine.resume(arg.take()) {
+                        ListItemHrefsResult::Ok(hrefs) => hrefs,
+                        ListItemHrefsResult::Io(io) => break SyncCollectionResult::Io(io),
+                        ListItemHrefsResult::Err(err) => {
+                            let err = SyncCollectionError::ListItemsError(side, err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    for href in hrefs {
+                        if let Some(name) = href.path.file_name() {
+                            let name = name.to_string_lossy().to_string();
+                            self.item_paths.insert(href.path, (side, name));
+                        }
+                    }
+
+                    if side == SyncSide::A {
+                        let coroutine = ListItemHrefs::new(&self.b);
+                        self.state = State::ListItems(SyncSide::B, coroutine);
+                        continue;
+                    }
+
+                    if self.item_paths.is_empty() {
+                        if let Err(err) = self.plan(HashMap::new()) {
+                            break SyncCollectionResult::Err(err);
+                        }
+
+                        self.state = self.next_write_state();
+                        continue;
+                    }
+
+                    let fs = ReadFiles::new(self.item_paths.keys().cloned());
+                    self.state = State::ReadItems(fs);
+                }
+                State::ReadItems(fs) => {
+                    let mut contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break SyncCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = SyncCollectionError::ReadItemsError(err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    for (path, raw) in &contents {
+                        let Some((side, name)) = self.item_paths.remove(path) else {
+                            continue;
+                        };
+
+                        let etag = Etag::from_contents(raw);
+
+                        match side {
+                            SyncSide::A => self.hrefs_a.insert(name, etag),
+                            SyncSide::B => self.hrefs_b.insert(name, etag),
+                        };
+                    }
+
+                    // NOTE: contents of unchanged items are dropped,
+                    // their identifier is already known from the status
+                    let changed_paths = self.changed_paths();
+                    contents.retain(|path, _| changed_paths.contains(path));
+
+                    if let Err(err) = self.plan(contents) {
+                        break SyncCollectionResult::Err(err);
+                    }
+
+                    self.state = self.next_write_state();
+                }
+                State::CreateTempFiles(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break SyncCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = SyncCollectionError::CreateTempFilesError(err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.state = self.next_write_state();
+                }
+                State::MoveTempFiles(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break SyncCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = SyncCollectionError::SaveFilesError(err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.state = self.next_write_state();
+                }
+                State::UpdateItem(coroutine) => {
+                    match coroutine.resume(arg.take()) {
+                        UpdateItemResult::Ok => (),
+                        UpdateItemResult::Io(io) => break SyncCollectionResult::Io(io),
+                        UpdateItemResult::Err(UpdateItemError::PreconditionFailed(path)) => {
+                            let fs = ReadFile::new(&path);
+                            self.state = State::ReadModifiedItem(path, fs);
+                            continue;
+                        }
+                        UpdateItemResult::Err(err) => {
+                            let err = SyncCollectionError::UpdateItemError(err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.state = self.next_write_state();
+                }
+                State::DeleteItem(coroutine) => {
+                    match coroutine.resume(arg.take()) {
+                        DeleteItemResult::Ok => (),
+                        DeleteItemResult::Io(io) => break SyncCollectionResult::Io(io),
+                        DeleteItemResult::Err(DeleteItemError::PreconditionFailed(path)) => {
+                            let fs = ReadFile::new(&path);
+                            self.state = State::ReadModifiedItem(path, fs);
+                            continue;
+                        }
+                        DeleteItemResult::Err(err) => {
+                            let err = SyncCollectionError::DeleteItemError(err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.state = self.next_write_state();
+                }
+                State::ReadModifiedItem(path, fs) => {
+                    // NOTE: the item may have been deleted in the
+                    // meantime, see crate::runtime::HandBack
+                    if matches!(&arg, Some(FsIo::ReadFile(Err(failed))) if failed == path) {
+                        arg = None;
+                        self.conflict_modified(None);
+                        self.state = self.next_write_state();
+                        continue;
+                    }
+
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break SyncCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = SyncCollectionError::ReadModifiedItemError(err);
+                            break SyncCollectionResult::Err(err);
+                        }
+                    };
+
+                    let item = match parse_item(path.clone(), contents) {
+                        Ok(item) => Some(item),
+                        Err(err) => {
+                            warn!("skip invalid Vdir item at {}: {err}", path.display());
+                            None
+                        }
+                    };
+
+                    self.conflict_modified(item);
+                    self.state = self.next_write_state();
+                }
+                State::Done => {
+                    break SyncCollectionResult::Ok(mem::take(&mut self.report));
+                }
+            }
+        }
+    }
+
+    /// Returns the paths of items that changed since the last
+    /// synchronization, on both sides.
+    ///
+    /// Only these items need to be parsed: the identifier of
+    /// unchanged items is already known from the status.
+    fn changed_paths(&self) -> HashSet<PathBuf> {
+        let mut known_a = HashSet::new();
+        let mut known_b = HashSet::new();
+
+        for item in self.status.items.values() {
+            known_a.insert((&item.a.href, &item.a.etag));
+            known_b.insert((&item.b.href, &item.b.etag));
+        }
+
+        let changed_a = self
+            .hrefs_a
+            .iter()
+            .filter(|entry| !known_a.contains(entry))
+            .map(|(href, _)| self.a.join(href));
+
+        let changed_b = self
+            .hrefs_b
+            .iter()
+            .filter(|entry| !known_b.contains(entry))
+            .map(|(href, _)| self.b.join(href));
+
+        changed_a.chain(changed_b).collect()
+    }
+
+    /// Computes the changes to apply on both sides, as well as the
+    /// new status.
+    fn plan(&mut self, mut contents: HashMap<PathBuf, Vec<u8>>) -> Result<(), SyncCollectionError> {
+        let (mut items_a, skipped_a) = self.side_items(SyncSide::A, &mut contents)?;
+        let (mut items_b, skipped_b) = self.side_items(SyncSide::B, &mut contents)?;
+        let mut status = mem::take(&mut self.status).items;
+
+        let mut idents: HashSet<String> = HashSet::new();
+        idents.extend(items_a.keys().cloned());
+        idents.extend(items_b.keys().cloned());
+        idents.extend(status.keys().cloned());
+
+        for ident in idents {
+            let a = items_a.remove(&ident);
+            let b = items_b.remove(&ident);
+            let prev = status.remove(&ident);
+
+            // NOTE: an item that could not be parsed must not be
+            // considered as deleted, otherwise it would be deleted on
+            // the other side as well
+            if let Some(prev) = &prev {
+                if skipped_a.contains(&prev.a.href) || skipped_b.contains(&prev.b.href) {
+                    self.report.status.items.insert(ident, prev.clone());
+                    continue;
+                }
+            }
+
+            let operations = self.operations.len();
+            let prev_item = prev.clone();
+
+            let next = match (a, b, prev) {
+                (None, None, _) => None,
+                (Some(a), None, None) => Some(self.copy(&ident, SyncSide::B, a, None)),
+                (None, Some(b), None) => Some(self.copy(&ident, SyncSide::A, b, None)),
+                (Some(a), Some(b), None) => {
+                    if a.etag == b.etag {
+                        Some(status_item(a, b))
+                    } else {
+                        self.conflict(&ident, a, b, None)
+                    }
+                }
+                (Some(a), None, Some(prev)) => {
+                    if is_changed(&a, &prev.a) {
+                        Some(self.copy(&ident, SyncSide::B, a, None))
+                    } else {
+                        self.delete(&ident, SyncSide::A, a);
+                        None
+                    }
+                }
+                (None, Some(b), Some(prev)) => {
+                    if is_changed(&b, &prev.b) {
+                        Some(self.copy(&ident, SyncSide::A, b, None))
+                    } else {
+                        self.delete(&ident, SyncSide::B, b);
+                        None
+                    }
+                }
+                (Some(a), Some(b), Some(prev)) => {
+                    match (is_changed(&a, &prev.a), is_changed(&b, &prev.b)) {
+                        (false, false) => Some(status_item(a, b)),
+                        (true, false) => Some(self.copy(&ident, SyncSide::B, a, Some(b))),
+                        (false, true) => Some(self.copy(&ident, SyncSide::A, b, Some(a))),
+                        (true, true) if a.etag == b.etag => Some(status_item(a, b)),
+                        (true, true) => self.conflict(&ident, a, b, Some(prev)),
+                    }
+                }
+            };
+
+            // NOTE: the previous status is kept aside, so that it
+            // can be restored when an item is modified in the meantime
+            if self.operations.len() > operations {
+                self.prev_items.insert(ident.clone(), prev_item);
+            }
+
+            if let Some(next) = next {
+                self.report.status.items.insert(ident, next);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Identifies the items of the given side.
+    ///
+    /// Also returns the hrefs of the items that could not be
+    /// identified.
+    fn side_items(
+        &self,
+        side: SyncSide,
+        contents: &mut HashMap<PathBuf, Vec<u8>>,
+    ) -> Result<(HashMap<String, SideItem>, HashSet<String>), SyncCollectionError> {
+        let (root, hrefs) = match side {
+            SyncSide::A => (&self.a, &self.hrefs_a),
+            SyncSide::B => (&self.b, &self.hrefs_b),
+        };
+
+        let mut known = HashMap::new();
+
+        for (ident, item) in &self.status.items {
+            let href = match side {
+                SyncSide::A => &item.a,
+                SyncSide::B => &item.b,
+            };
+
+            known.insert((&href.href, &href.etag), ident);
+        }
+
+        let mut items = HashMap::new();
+        let mut skipped = HashSet::new();
+
+        for (href, etag) in hrefs {
+            let (ident, item_contents) = match known.get(&(href, etag)) {
+                Some(ident) => (ident.to_string(), None),
+                None => {
+                    let path = root.join(href);
+
+                    let Some(raw) = contents.remove(&path) else {
+                        warn!("skip unread Vdir item at {}", path.display());
+                        skipped.insert(href.clone());
+                        continue;
+                    };
+
+                    let item = match parse_item(path.clone(), raw.clone()) {
+                        Ok(item) => item,
+                        Err(err) => {
+                            warn!("skip invalid Vdir item at {}: {err}", path.display());
+                            skipped.insert(href.clone());
+                            continue;
+                        }
+                    };
+
+                    let ident = match item.kind.uid() {
+                        Some(uid) => uid.to_owned(),
+                        None => etag.to_string(),
+                    };
+
+                    (ident, Some((item, raw)))
+                }
+            };
+
+            let item = SideItem {
+                href: href.clone(),
+                etag: etag.clone(),
+                contents: item_contents,
+            };
+
+            if items.insert(ident.clone(), item).is_some() {
+                return Err(SyncCollectionError::DuplicateIdent(side, ident));
+            }
+        }
+
+        Ok((items, skipped))
+    }
+
+    /// Plans the copy of the given source item to the given target
+    /// side, and returns the associated status item.
+    ///
+    /// When a target item is given, it is replaced by the source
+    /// item. Otherwise a new item is created on the target side.
+    fn copy(
+        &mut self,
+        ident: &str,
+        target: SyncSide,
+        source: SideItem,
+        target_item: Option<SideItem>,
+    ) -> SyncStatusItem {
+        // NOTE: items are copied only when they changed since the
+        // last synchronization, so their contents are always read
+        let (item, raw) = source.contents.expect("changed Vdir item");
+
+        let href = match target_item {
+            Some(target_item) => {
+                let root = match target {
+                    SyncSide::A => &self.a,
+                    SyncSide::B => &self.b,
+                };
+
+                let update = Item {
+                    path: root.join(&target_item.href),
+                    kind: item.kind.clone(),
+                    etag: None,
+                    raw: Some(raw),
+                };
+
+                let change = SyncChange::Update(target, target_item.href.clone());
+                self.update(ident, change, update, target_item.etag, Some(item));
+                target_item.href
+            }
+            None => {
+                let hrefs = match target {
+                    SyncSide::A => &self.hrefs_a,
+                    SyncSide::B => &self.hrefs_b,
+                };
+
+                let href = if hrefs.contains_key(&source.href) {
+                    new_href(&item.kind)
+                } else {
+                    source.href.clone()
+                };
+
+                self.create(SyncChange::Create(target, href.clone()), raw);
+                href
+            }
+        };
+
+        let source_href = SyncStatusHref {
+            href: source.href,
+            etag: source.etag.clone(),
+        };
+
+        let target_href = SyncStatusHref {
+            href,
+            etag: source.etag,
+        };
+
+        match target {
+            SyncSide::A => SyncStatusItem {
+                a: target_href,
+                b: source_href,
+            },
+            SyncSide::B => SyncStatusItem {
+                a: source_href,
+                b: target_href,
+            },
+        }
+    }
+
+    /// Plans the creation of an item with the given contents.
+    fn create(&mut self, change: SyncChange, raw: Vec<u8>) {
+        let (side, href) = match &change {
+            SyncChange::Create(side, href) => (*side, href),
+            SyncChange::Update(..) | SyncChange::Delete(..) => {
+                unreachable!("create Vdir item on update or delete")
+            }
+        };
+
+        let root = match side {
+            SyncSide::A => &self.a,
+            SyncSide::B => &self.b,
+        };
+
+        debug!("plan {change:?}");
+
+        let path = root.join(href);
+        let path_tmp = tmp_path(&path);
+        self.contents.insert(path_tmp.clone(), raw);
+        self.rename_paths.push((path_tmp, path));
+        self.report.changes.push(change);
+    }
+
+    /// Plans the update of the given item, as long as the current
+    /// item still matches the given etag.
+    ///
+    /// The item of the other side is reported as part of the
+    /// conflict when the item has been modified in the meantime.
+    fn update(
+        &mut self,
+        ident: &str,
+        change: SyncChange,
+        item: Item,
+        etag: Etag,
+        other: Option<Item>,
+    ) {
+        debug!("plan {change:?}");
+
+        let write = Write {
+            ident: ident.to_owned(),
+            change: change.clone(),
+            other,
+        };
+
+        self.operations
+            .push_back(Operation::Update(write, item, etag));
+        self.report.changes.push(change);
+    }
+
+    /// Plans the deletion of the given item on the given side, as
+    /// long as the current item still matches its etag.
+    fn delete(&mut self, ident: &str, side: SyncSide, item: SideItem) {
+        let root = match side {
+            SyncSide::A => &self.a,
+            SyncSide::B => &self.b,
+        };
+
+        let change = SyncChange::Delete(side, item.href.clone());
+        debug!("plan {change:?}");
+
+        let write = Write {
+            ident: ident.to_owned(),
+            change: change.clone(),
+            other: None,
+        };
+
+        let path = root.join(&item.href);
+        self.operations
+            .push_back(Operation::Delete(write, path, item.etag));
+        self.report.changes.push(change);
+    }
+
+    /// Reports the item of the current write as a conflict, since
+    /// it has been modified in the meantime.
+    ///
+    /// The change is removed from the report, and the previous
+    /// status of the item is restored so that the change is detected
+    /// again during the next synchronization.
+    fn conflict_modified(&mut self, current: Option<Item>) {
+        let Some(write) = self.write.take() else {
+            return;
+        };
+
+        warn!("skip Vdir item {} modified in the meantime", write.ident);
+        self.report.changes.retain(|change| *change != write.change);
+
+        match self.prev_items.get(&write.ident).cloned().flatten() {
+            Some(prev) => self.report.status.items.insert(write.ident.clone(), prev),
+            None => self.report.status.items.remove(&write.ident),
+        };
+
+        let side = match &write.change {
+            SyncChange::Create(side, _)
+            | SyncChange::Update(side, _)
+            | SyncChange::Delete(side, _) => *side,
+        };
+
+        let (a, b) = match side {
+            SyncSide::A => (current, write.other),
+            SyncSide::B => (write.other, current),
+        };
+
+        let ident = write.ident;
+        self.report.conflicts.push(SyncConflict { ident, a, b });
+    }
+
+    /// Resolves the conflict between the given items using the
+    /// conflict strategy, and returns the associated status item.
+    ///
+    /// Unresolved conflicts are reported, and the previous status
+    /// item is returned so that the conflict is detected again during
+    /// the next synchronization.
+    fn conflict(
+        &mut self,
+        ident: &str,
+        a: SideItem,
+        b: SideItem,
+        prev: Option<SyncStatusItem>,
+    ) -> Option<SyncStatusItem> {
+        // NOTE: items are in conflict only when they changed on both
+        // sides, so their contents are always read
+        let (Some((item_a, _)), Some((item_b, _))) = (&a.contents, &b.contents) else {
+            return prev;
+        };
+
+        let resolution = self.strategy.resolve(item_a, item_b);
+        debug!("conflict detected for Vdir item {ident}: {resolution:?}");
+
+        match resolution {
+            ConflictResolution::Unresolved => {
+                if let (Some((a, _)), Some((b, _))) = (a.contents, b.contents) {
+                    let ident = ident.to_owned();
+                    let (a, b) = (Some(a), Some(b));
+                    let conflict = SyncConflict { ident, a, b };
+                    self.report.conflicts.push(conflict);
+                }
+
+                prev
+            }
+            ConflictResolution::KeepA => Some(self.copy(ident, SyncSide::B, a, Some(b))),
+            ConflictResolution::KeepB => Some(self.copy(ident, SyncSide::A, b, Some(a))),
+            ConflictResolution::KeepBoth(kind) => {
+                let raw = kind.to_string().into_bytes();
+                let etag = Etag::from_contents(&raw);
+                let href = new_href(&kind);
+
+                self.create(SyncChange::Create(SyncSide::A, href.clone()), raw.clone());
+                self.create(SyncChange::Create(SyncSide::B, href.clone()), raw);
+
+                let new_ident = match kind.uid() {
+                    Some(uid) => uid.to_owned(),
+                    None => etag.to_string(),
+                };
+
+                let item = SyncStatusItem {
+                    a: SyncStatusHref {
+                        href: href.clone(),
+                        etag: etag.clone(),
+                    },
+                    b: SyncStatusHref { href, etag },
+                };
+
+                self.report.status.items.insert(new_ident, item);
+
+                Some(self.copy(ident, SyncSide::B, a, Some(b)))
+            }
+            ConflictResolution::Merge(kind) => {
+                let etag = Etag::from_contents(kind.to_string().into_bytes());
+                let item_a = a.contents.map(|(item, _)| item);
+                let item_b = b.contents.map(|(item, _)| item);
+
+                let update_a = Item {
+                    path: self.a.join(&a.href),
+                    kind: kind.clone(),
+                    etag: None,
+                    raw: None,
+                };
+
+                let change = SyncChange::Update(SyncSide::A, a.href.clone());
+                self.update(ident, change, update_a, a.etag, item_b.clone());
+
+                let update_b = Item {
+                    path: self.b.join(&b.href),
+                    kind,
+                    etag: None,
+                    raw: None,
+                };
+
+                let change = SyncChange::Update(SyncSide::B, b.href.clone());
+                self.update(ident, change, update_b, b.etag, item_a);
+
+                Some(SyncStatusItem {
+                    a: SyncStatusHref {
+                        href: a.href,
+                        etag: etag.clone(),
+                    },
+                    b: SyncStatusHref { href: b.href, etag },
+                })
+            }
+        }
+    }
+
+    fn next_write_state(&mut self) -> State {
+        if !self.contents.is_empty() {
+            let fs = CreateFiles::new(self.contents.drain());
+            return State::CreateTempFiles(fs);
+        }
+
+        if !self.rename_paths.is_empty() {
+            let fs = Rename::new(self.rename_paths.drain(..));
+            return State::MoveTempFiles(fs);
+        }
+
+        match self.operations.pop_front() {
+            Some(Operation::Update(write, item, etag)) => {
+                self.write = Some(write);
+                State::UpdateItem(UpdateItem::new(item, Some(etag)))
+            }
+            Some(Operation::Delete(write, path, etag)) => {
+                self.write = Some(write);
+                State::DeleteItem(DeleteItem::new(path, Some(etag)))
+            }
+            None => State::Done,
+        }
+    }
+}
+
+fn new_href(kind: &ItemKind) -> String {
+    format!("{}.{}", Uuid::new_v4(), kind.extension())
+}
+
+fn is_changed(item: &SideItem, prev: &SyncStatusHref) -> bool {
+    item.href != prev.href || item.etag != prev.etag
+}
+
+fn status_item(a: SideItem, b: SideItem) -> SyncStatusItem {
+    SyncStatusItem {
+        a: SyncStatusHref {
+            href: a.href,
+            etag: a.etag,
+        },
+        b: SyncStatusHref {
+            href: b.href,
+            etag: b.etag,
+        },
+    }
+}
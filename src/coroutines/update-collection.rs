@@ -1,22 +1,33 @@
 //! I/O-free coroutine to update a Vdir collection.
 
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 use io_fs::{
-    coroutines::{create_files::CreateFiles, rename::Rename},
+    coroutines::{
+        create_files::CreateFiles, read_dir::ReadDir, remove_files::RemoveFiles, rename::Rename,
+    },
     error::{FsError, FsResult},
     io::FsIo,
 };
 use thiserror::Error;
 
 use crate::{
-    collection::Collection,
-    constants::{COLOR, DESCRIPTION, DISPLAYNAME, TMP},
+    collection::{Collection, CollectionPatch, MetadataPatch},
+    constants::{COLOR, DESCRIPTION, DISPLAYNAME},
+    tmp::tmp_path,
 };
 
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
 pub enum UpdateCollectionError {
+    /// An error occured during the listing of existing metadata
+    /// files.
+    #[error("List Vdir collection metadata")]
+    ListMetadata(#[source] FsError),
+
     /// An error occured during the creation of new metadata files.
     #[error("Create new Vdir collection metadata")]
     CreateNewMetadata(#[source] FsError),
@@ -25,6 +36,10 @@ pub enum UpdateCollectionError {
     /// metadata files.
     #[error("Save Vdir collection metadata")]
     SaveMetadata(#[source] FsError),
+
+    /// An error occured during the removal of unset metadata files.
+    #[error("Remove Vdir collection metadata")]
+    RemoveMetadata(#[source] FsError),
 }
 
 /// Output emitted when the coroutine terminates its progression.
@@ -43,54 +58,104 @@ pub enum UpdateCollectionResult {
 
 #[derive(Debug)]
 enum State {
-    CreateMetadataTempFiles(CreateFiles, Vec<(PathBuf, PathBuf)>),
+    ListMetadata(ReadDir),
+    CreateMetadataTempFiles(CreateFiles),
     MoveMetadataFiles(Rename),
+    RemoveMetadata(RemoveFiles),
 }
 
 /// I/O-free coroutine to update a Vdir collection.
 #[derive(Debug)]
 pub struct UpdateCollection {
-    state: State,
+    contents: HashMap<PathBuf, Vec<u8>>,
+    rename_paths: Vec<(PathBuf, PathBuf)>,
+    remove_paths: HashSet<PathBuf>,
+    state: Option<State>,
 }
 
 impl UpdateCollection {
     /// Creates a new coroutine from the given collection.
-    pub fn new(mut collection: Collection) -> Self {
-        let mut contents = HashMap::new();
-        let mut rename_paths = Vec::new();
+    ///
+    /// Metadata set to `None` are kept as they are. See
+    /// [`UpdateCollection::patch`] to unset metadata.
+    pub fn new(collection: Collection) -> Self {
+        let path = collection.path.clone();
+        Self::patch(path, collection.into())
+    }
 
-        if let Some(name) = collection.display_name.take() {
-            let path = collection.path.join(DISPLAYNAME);
-            let tmp_path = path.with_extension(TMP);
-            contents.insert(tmp_path.clone(), name.into_bytes());
-            rename_paths.push((tmp_path, path));
-        }
+    /// Creates a new coroutine from the given collection's path and
+    /// metadata patch.
+    pub fn patch(path: impl Into<PathBuf>, patch: CollectionPatch) -> Self {
+        let path = path.into();
 
-        if let Some(desc) = collection.description.take() {
-            let path = collection.path.join(DESCRIPTION);
-            let tmp_path = path.with_extension(TMP);
-            contents.insert(tmp_path.clone(), desc.into_bytes());
-            rename_paths.push((tmp_path, path));
+        let mut contents = HashMap::new();
+        let mut rename_paths = Vec::new();
+        let mut remove_paths = HashSet::new();
+
+        let patches = [
+            (DISPLAYNAME, patch.display_name),
+            (DESCRIPTION, patch.description),
+            (COLOR, patch.color),
+        ];
+
+        for (name, patch) in patches {
+            let path = path.join(name);
+
+            match patch {
+                MetadataPatch::Keep => (),
+                MetadataPatch::Set(value) => {
+                    let path_tmp = tmp_path(&path);
+                    contents.insert(path_tmp.clone(), value.into_bytes());
+                    rename_paths.push((path_tmp, path));
+                }
+                MetadataPatch::Unset => {
+                    remove_paths.insert(path);
+                }
+            }
         }
 
-        if let Some(color) = collection.color.take() {
-            let path = collection.path.join(COLOR);
-            let tmp_path = path.with_extension(TMP);
-            contents.insert(tmp_path.clone(), color.into_bytes());
-            rename_paths.push((tmp_path, path));
+        // NOTE: existing metadata files need to be listed first, so
+        // that only existing ones are removed
+        let state = if remove_paths.is_empty() {
+            let fs = CreateFiles::new(contents.drain());
+            State::CreateMetadataTempFiles(fs)
+        } else {
+            let fs = ReadDir::new(path);
+            State::ListMetadata(fs)
+        };
+
+        Self {
+            contents,
+            rename_paths,
+            remove_paths,
+            state: Some(state),
         }
-
-        let fs = CreateFiles::new(contents);
-        let state = State::CreateMetadataTempFiles(fs, rename_paths);
-
-        Self { state }
     }
 
     /// Makes the coroutine progress.
     pub fn resume(&mut self, mut arg: Option<FsIo>) -> UpdateCollectionResult {
         loop {
-            match &mut self.state {
-                State::CreateMetadataTempFiles(fs, rename_paths) => {
+            let Some(state) = &mut self.state else {
+                break UpdateCollectionResult::Ok;
+            };
+
+            match state {
+                State::ListMetadata(fs) => {
+                    let paths = match fs.resume(arg.take()) {
+                        FsResult::Ok(paths) => paths,
+                        FsResult::Io(io) => break UpdateCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = UpdateCollectionError::ListMetadata(err);
+                            break UpdateCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.remove_paths.retain(|path| paths.contains(path));
+
+                    let fs = CreateFiles::new(self.contents.drain());
+                    self.state = Some(State::CreateMetadataTempFiles(fs));
+                }
+                State::CreateMetadataTempFiles(fs) => {
                     match fs.resume(arg.take()) {
                         FsResult::Ok(()) => (),
                         FsResult::Io(io) => break UpdateCollectionResult::Io(io),
@@ -100,8 +165,8 @@ impl UpdateCollection {
                         }
                     };
 
-                    let fs = Rename::new(rename_paths.drain(..));
-                    self.state = State::MoveMetadataFiles(fs);
+                    let fs = Rename::new(self.rename_paths.drain(..));
+                    self.state = Some(State::MoveMetadataFiles(fs));
                 }
                 State::MoveMetadataFiles(fs) => {
                     match fs.resume(arg.take()) {
@@ -113,7 +178,25 @@ impl UpdateCollection {
                         }
                     };
 
-                    break UpdateCollectionResult::Ok;
+                    if self.remove_paths.is_empty() {
+                        self.state = None;
+                        continue;
+                    }
+
+                    let fs = RemoveFiles::new(self.remove_paths.drain());
+                    self.state = Some(State::RemoveMetadata(fs));
+                }
+                State::RemoveMetadata(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break UpdateCollectionResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = UpdateCollectionError::RemoveMetadata(err);
+                            break UpdateCollectionResult::Err(err);
+                        }
+                    };
+
+                    self.state = None;
                 }
             }
         }
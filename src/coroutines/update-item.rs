@@ -3,13 +3,15 @@
 use std::path::PathBuf;
 
 use io_fs::{
-    coroutines::{create_file::CreateFile, rename::Rename},
+    coroutines::{
+        create_file::CreateFile, read_file::ReadFile, remove_file::RemoveFile, rename::Rename,
+    },
     error::{FsError, FsResult},
     io::FsIo,
 };
 use thiserror::Error;
 
-use crate::{constants::TMP, item::Item};
+use crate::{etag::Etag, item::Item, tmp::tmp_path};
 
 /// Errors that can occur during the coroutine progression.
 #[derive(Clone, Debug, Error)]
@@ -18,6 +20,20 @@ pub enum UpdateItemError {
     #[error("Create temporary Vdir item file error")]
     CreateTempFile(#[source] FsError),
 
+    /// An error occured while reading the current item file.
+    #[error("Read current Vdir item file error")]
+    ReadFile(#[source] FsError),
+
+    /// The current item file does not match the expected entity
+    /// tag: it has been changed by someone else in the meantime.
+    #[error("Vdir item at {0} has been modified in the meantime")]
+    PreconditionFailed(PathBuf),
+
+    /// An error occured during the removal of the temporary item
+    /// file, after a precondition failure or a read error.
+    #[error("Remove temporary Vdir item file error")]
+    RemoveTempFile(#[source] FsError),
+
     /// An error occured during the switch between old and new item
     /// files.
     #[error("Save Vdir item file error")]
@@ -41,6 +57,8 @@ pub enum UpdateItemResult {
 #[derive(Debug)]
 enum State {
     CreateTempItem(CreateFile),
+    CheckEtag(ReadFile),
+    RemoveTempItem(RemoveFile),
     MoveItem(Rename),
 }
 
@@ -49,19 +67,35 @@ enum State {
 pub struct UpdateItem {
     path: PathBuf,
     path_tmp: PathBuf,
+    etag: Option<Etag>,
+    error: Option<UpdateItemError>,
     state: State,
 }
 
 impl UpdateItem {
     /// Creates a new coroutine from the given item.
-    pub fn new(item: Item) -> Self {
-        let path_tmp = item.path.with_extension(TMP);
-        let fs = CreateFile::new(&path_tmp, item.to_string().into_bytes());
+    ///
+    /// When an entity tag is given, the coroutine checks that the
+    /// current item file still matches it right before replacing
+    /// it, and fails with [`UpdateItemError::PreconditionFailed`]
+    /// otherwise (similar to the HTTP `If-Match` header).
+    ///
+    /// A current item file that cannot be read, for example because
+    /// it has been deleted in the meantime, fails the precondition
+    /// as well: resume the coroutine with the unprocessed request
+    /// ([`FsIo::ReadFile`] holding the item path) when the runtime
+    /// fails to read it (see [`crate::runtime::HandBack`]). The
+    /// temporary item file is removed in both cases.
+    pub fn new(item: Item, etag: Option<Etag>) -> Self {
+        let path_tmp = tmp_path(&item.path);
+        let fs = CreateFile::new(&path_tmp, item.to_bytes());
         let state = State::CreateTempItem(fs);
 
         Self {
             path: item.path,
             path_tmp,
+            etag,
+            error: None,
             state,
         }
     }
@@ -79,8 +113,52 @@ impl UpdateItem {
                             break UpdateItemResult::Err(err);
                         }
                     };
-                    let fs = Rename::new(Some((&self.path_tmp, &self.path)));
-                    self.state = State::MoveItem(fs);
+
+                    self.state = if self.etag.is_some() {
+                        State::CheckEtag(ReadFile::new(&self.path))
+                    } else {
+                        State::MoveItem(Rename::new(Some((&self.path_tmp, &self.path))))
+                    };
+                }
+                State::CheckEtag(fs) => {
+                    if matches!(&arg, Some(FsIo::ReadFile(Err(failed))) if *failed == self.path) {
+                        arg = None;
+                        self.state = State::RemoveTempItem(RemoveFile::new(&self.path_tmp));
+                        continue;
+                    }
+
+                    let contents = match fs.resume(arg.take()) {
+                        FsResult::Ok(contents) => contents,
+                        FsResult::Io(io) => break UpdateItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            self.error = Some(UpdateItemError::ReadFile(err));
+                            self.state = State::RemoveTempItem(RemoveFile::new(&self.path_tmp));
+                            continue;
+                        }
+                    };
+
+                    self.state = if self.etag == Some(Etag::from_contents(contents)) {
+                        State::MoveItem(Rename::new(Some((&self.path_tmp, &self.path))))
+                    } else {
+                        State::RemoveTempItem(RemoveFile::new(&self.path_tmp))
+                    };
+                }
+                State::RemoveTempItem(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break UpdateItemResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = UpdateItemError::RemoveTempFile(err);
+                            break UpdateItemResult::Err(err);
+                        }
+                    };
+
+                    let err = match self.error.take() {
+                        Some(err) => err,
+                        None => UpdateItemError::PreconditionFailed(self.path.clone()),
+                    };
+
+                    break UpdateItemResult::Err(err);
                 }
                 State::MoveItem(fs) => {
                     match fs.resume(arg.take()) {
@@ -0,0 +1,106 @@
+//! I/O-free coroutine to update a Vdir synchronization status.
+
+use std::path::PathBuf;
+
+use io_fs::{
+    coroutines::{create_file::CreateFile, rename::Rename},
+    error::{FsError, FsResult},
+    io::FsIo,
+};
+use thiserror::Error;
+
+use crate::{sync::SyncStatus, tmp::tmp_path};
+
+/// Errors that can occur during the coroutine progression.
+#[derive(Clone, Debug, Error)]
+pub enum UpdateSyncStatusError {
+    /// An error occured during the creation of the temporary status
+    /// file.
+    #[error("Create temporary Vdir sync status file error")]
+    CreateTempFile(#[source] FsError),
+
+    /// An error occured during the switch between old and new
+    /// status files.
+    #[error("Save Vdir sync status file error")]
+    SaveFile(#[source] FsError),
+}
+
+/// Output emitted when the coroutine terminates its progression.
+#[derive(Clone, Debug)]
+pub enum UpdateSyncStatusResult {
+    /// The coroutine successfully terminated its progression.
+    Ok,
+
+    /// The coroutine encountered an error.
+    Err(UpdateSyncStatusError),
+
+    /// An I/O needs to be processed in order to make the coroutine
+    /// progress further.
+    Io(FsIo),
+}
+
+#[derive(Debug)]
+enum State {
+    CreateTempStatus(CreateFile),
+    MoveStatus(Rename),
+}
+
+/// I/O-free coroutine to update a Vdir synchronization status.
+///
+/// The status file is created if it does not exist yet.
+///
+/// See [`SyncStatus::to_bytes`] for the status file format.
+#[derive(Debug)]
+pub struct UpdateSyncStatus {
+    path: PathBuf,
+    path_tmp: PathBuf,
+    state: State,
+}
+
+impl UpdateSyncStatus {
+    /// Creates a new coroutine from the given status file path and
+    /// status.
+    pub fn new(path: impl Into<PathBuf>, status: &SyncStatus) -> Self {
+        let path = path.into();
+        let path_tmp = tmp_path(&path);
+        let fs = CreateFile::new(&path_tmp, status.to_bytes());
+        let state = State::CreateTempStatus(fs);
+
+        Self {
+            path,
+            path_tmp,
+            state,
+        }
+    }
+
+    /// Makes the coroutine progress.
+    pub fn resume(&mut self, mut arg: Option<FsIo>) -> UpdateSyncStatusResult {
+        loop {
+            match &mut self.state {
+                State::CreateTempStatus(fs) => {
+                    match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => (),
+                        FsResult::Io(io) => break UpdateSyncStatusResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = UpdateSyncStatusError::CreateTempFile(err);
+                            break UpdateSyncStatusResult::Err(err);
+                        }
+                    };
+
+                    let fs = Rename::new(Some((&self.path_tmp, &self.path)));
+                    self.state = State::MoveStatus(fs);
+                }
+                State::MoveStatus(fs) => {
+                    break match fs.resume(arg.take()) {
+                        FsResult::Ok(()) => UpdateSyncStatusResult::Ok,
+                        FsResult::Io(io) => UpdateSyncStatusResult::Io(io),
+                        FsResult::Err(err) => {
+                            let err = UpdateSyncStatusError::SaveFile(err);
+                            UpdateSyncStatusResult::Err(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Module dedicated to the property-level diff of items.
+
+use std::fmt;
+
+use calcard::{
+    icalendar::{
+        ICalendar, ICalendarComponent, ICalendarComponentType, ICalendarEntry, ICalendarProperty,
+        ICalendarValue,
+    },
+    vcard::{VCard, VCardEntry, VCardVersion},
+};
+use thiserror::Error;
+
+use crate::item::ItemKind;
+
+/// Errors that can occur during the diff.
+#[derive(Clone, Debug, Error)]
+pub enum DiffError {
+    /// The items do not share the same format.
+    #[error("Cannot diff items of different formats")]
+    FormatMismatch,
+}
+
+/// The property-level diff between two items.
+///
+/// The [`fmt::Display`] implementation renders the diff line by
+/// line, prefixing removed properties with `-` and added properties
+/// with `+`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ItemDiff {
+    /// The vCard diff variant.
+    Vcard(VcardDiff),
+
+    /// The iCalendar diff variant.
+    Ical(IcalDiff),
+}
+
+impl ItemDiff {
+    /// Returns `true` if both items share the same properties.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Vcard(diff) => diff.properties.is_empty(),
+            Self::Ical(diff) => diff.components.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for ItemDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vcard(diff) => diff.fmt(f),
+            Self::Ical(diff) => diff.fmt(f),
+        }
+    }
+}
+
+/// The property-level diff between two vCards.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VcardDiff {
+    /// The version of the new vCard, used to render properties.
+    pub version: VCardVersion,
+
+    /// The changed properties.
+    pub properties: Vec<PropertyDiff<VCardEntry>>,
+}
+
+impl fmt::Display for VcardDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let is_v4 = self.version == VCardVersion::V4_0;
+
+        for property in &self.properties {
+            property.fmt_with(f, |out, entry| entry.write_to(out, is_v4))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The component-level diff between two iCalendars.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IcalDiff {
+    /// The added, removed or changed components.
+    pub components: Vec<ComponentDiff>,
+}
+
+impl fmt::Display for IcalDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for component in &self.components {
+            component.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The property-level diff of an iCalendar component.
+///
+/// Components are matched by type, UID and RECURRENCE-ID, then by
+/// order of appearance.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComponentDiff {
+    /// The kind of change of the component.
+    pub change: ComponentChange,
+
+    /// The type of the component.
+    pub component_type: ICalendarComponentType,
+
+    /// The UID of the component, if any.
+    pub uid: Option<String>,
+
+    /// The changed properties.
+    ///
+    /// For added or removed components, contains all their
+    /// properties.
+    pub properties: Vec<PropertyDiff<ICalendarEntry>>,
+}
+
+impl fmt::Display for ComponentDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = match self.change {
+            ComponentChange::Added => '+',
+            ComponentChange::Removed => '-',
+            ComponentChange::Changed => '~',
+        };
+
+        write!(f, "{change} {}", self.component_type.as_str())?;
+
+        if let Some(uid) = &self.uid {
+            write!(f, " {uid}")?;
+        }
+
+        writeln!(f)?;
+
+        for property in &self.properties {
+            property.fmt_with(f, |out, entry| entry.write_to(out))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The kind of change of an iCalendar component.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ComponentChange {
+    /// The component only exists in the new item.
+    Added,
+
+    /// The component only exists in the old item.
+    Removed,
+
+    /// The component exists in both items, with different
+    /// properties.
+    Changed,
+}
+
+/// The change of a property, including its parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PropertyDiff<T> {
+    /// The property only exists in the new item.
+    Added(T),
+
+    /// The property only exists in the old item.
+    Removed(T),
+
+    /// The property changed from the first value to the second one.
+    Changed(T, T),
+}
+
+impl<T> PropertyDiff<T> {
+    fn fmt_with(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        write: impl Fn(&mut String, &T) -> fmt::Result,
+    ) -> fmt::Result {
+        let line = |f: &mut fmt::Formatter<'_>, prefix: char, entry: &T| {
+            let mut out = String::new();
+            write(&mut out, entry)?;
+            // NOTE: entries are written folded and CRLF-terminated
+            let out = out.replace("\r\n ", "");
+            writeln!(f, "{prefix} {}", out.trim_end())
+        };
+
+        match self {
+            Self::Added(entry) => line(f, '+', entry),
+            Self::Removed(entry) => line(f, '-', entry),
+            Self::Changed(old, new) => {
+                line(f, '-', old)?;
+                line(f, '+', new)
+            }
+        }
+    }
+}
+
+/// Computes the property-level diff between the old and the new
+/// versions of an item.
+pub fn diff(old: &ItemKind, new: &ItemKind) -> Result<ItemDiff, DiffError> {
+    match (old, new) {
+        (ItemKind::Vcard(old), ItemKind::Vcard(new)) => Ok(ItemDiff::Vcard(diff_vcard(old, new))),
+        (ItemKind::Ical(old), ItemKind::Ical(new)) => Ok(ItemDiff::Ical(diff_ical(old, new))),
+        _ => Err(DiffError::FormatMismatch),
+    }
+}
+
+fn diff_vcard(old: &VCard, new: &VCard) -> VcardDiff {
+    let key = |entry: &VCardEntry| (entry.group.clone(), entry.name.clone());
+
+    VcardDiff {
+        version: new.version().unwrap_or_default(),
+        properties: diff_entries(&old.entries, &new.entries, key),
+    }
+}
+
+type ComponentKey = (
+    ICalendarComponentType,
+    Option<String>,
+    Option<Vec<ICalendarValue>>,
+);
+
+fn diff_ical(old: &ICalendar, new: &ICalendar) -> IcalDiff {
+    let key = |component: &ICalendarComponent| -> ComponentKey {
+        let recurrence_id = component
+            .property(&ICalendarProperty::RecurrenceId)
+            .map(|entry| entry.values.clone());
+
+        (
+            component.component_type.clone(),
+            component.uid().map(ToOwned::to_owned),
+            recurrence_id,
+        )
+    };
+
+    let old_components = group_entries(&old.components, key);
+    let new_components = group_entries(&new.components, key);
+    let mut components = Vec::new();
+
+    for (key, old, new) in zip_groups(&old_components, &new_components) {
+        let count = old.len().max(new.len());
+
+        for index in 0..count {
+            let (change, properties) = match (old.get(index), new.get(index)) {
+                (Some(old), Some(new)) => {
+                    let key = |entry: &ICalendarEntry| entry.name.clone();
+                    let properties = diff_entries(&old.entries, &new.entries, key);
+
+                    if properties.is_empty() {
+                        continue;
+                    }
+
+                    (ComponentChange::Changed, properties)
+                }
+                (None, Some(new)) => {
+                    let properties = new.entries.iter().cloned().map(PropertyDiff::Added);
+                    (ComponentChange::Added, properties.collect())
+                }
+                (Some(old), None) => {
+                    let properties = old.entries.iter().cloned().map(PropertyDiff::Removed);
+                    (ComponentChange::Removed, properties.collect())
+                }
+                (None, None) => continue,
+            };
+
+            let (component_type, uid, _) = key.clone();
+
+            components.push(ComponentDiff {
+                change,
+                component_type,
+                uid,
+                properties,
+            });
+        }
+    }
+
+    IcalDiff { components }
+}
+
+/// Computes the diff between entries grouped by the given key.
+///
+/// Within a group, entries found on both sides are left aside, and
+/// remaining entries are paired in order of appearance to form
+/// changes. Unpaired entries are considered added or removed.
+fn diff_entries<T, K>(old: &[T], new: &[T], key: impl Fn(&T) -> K) -> Vec<PropertyDiff<T>>
+where
+    T: Clone + PartialEq,
+    K: PartialEq,
+{
+    let old = group_entries(old, &key);
+    let new = group_entries(new, &key);
+    let mut diff = Vec::new();
+
+    for (_, old, new) in zip_groups(&old, &new) {
+        let mut removed: Vec<&T> = old.iter().filter(|entry| !new.contains(entry)).collect();
+        let mut added: Vec<&T> = new.iter().filter(|entry| !old.contains(entry)).collect();
+
+        let count = removed.len().min(added.len());
+
+        for (old, new) in removed.drain(..count).zip(added.drain(..count)) {
+            diff.push(PropertyDiff::Changed(old.clone(), new.clone()));
+        }
+
+        diff.extend(removed.into_iter().cloned().map(PropertyDiff::Removed));
+        diff.extend(added.into_iter().cloned().map(PropertyDiff::Added));
+    }
+
+    diff
+}
+
+/// Groups entries by the given key, in their order of appearance.
+pub(crate) fn group_entries<T: Clone, K: PartialEq>(
+    entries: &[T],
+    key: impl Fn(&T) -> K,
+) -> Vec<(K, Vec<T>)> {
+    let mut groups: Vec<(K, Vec<T>)> = Vec::new();
+
+    for entry in entries {
+        let key = key(entry);
+
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, entries)) => entries.push(entry.clone()),
+            None => groups.push((key, vec![entry.clone()])),
+        }
+    }
+
+    groups
+}
+
+/// Pairs the groups sharing the same key, keeping the order of the
+/// old groups followed by the groups only found in the new ones.
+fn zip_groups<'a, K: PartialEq, T>(
+    old: &'a [(K, Vec<T>)],
+    new: &'a [(K, Vec<T>)],
+) -> Vec<(&'a K, &'a [T], &'a [T])> {
+    let find = |groups: &'a [(K, Vec<T>)], key: &K| -> &'a [T] {
+        groups
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, entries)| entries.as_slice())
+            .unwrap_or_default()
+    };
+
+    let old_keys = old
+        .iter()
+        .map(|(key, entries)| (key, entries.as_slice(), find(new, key)));
+
+    let new_keys = new
+        .iter()
+        .filter(|(key, _)| !old.iter().any(|(k, _)| k == key))
+        .map(|(key, entries)| (key, &[][..], entries.as_slice()));
+
+    old_keys.chain(new_keys).collect()
+}
@@ -0,0 +1,60 @@
+//! Module dedicated to the Vdir item's entity tag.
+
+use std::fmt;
+
+use sha1_smol::Sha1;
+
+/// The Vdir item's entity tag (ETag).
+///
+/// An entity tag is an opaque string that changes whenever the
+/// item's file changes. Since coroutines only communicate with the
+/// filesystem through [`io_fs::io::FsIo`], which does not expose
+/// file metadata, the entity tag is derived from the raw contents of
+/// the item's file (SHA-1 hex digest).
+///
+/// This differs from vdirsyncer and khal, which derive entity tags
+/// from file metadata (modification time, inode):
+///
+/// - Entity tags never match the vdirsyncer ones, see
+///   [`crate::sync::SyncStatus::from_vdirsyncer_json`].
+///
+/// - Computing an entity tag requires reading the whole item file,
+///   so entity tags do not allow cheap change detection.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Etag(String);
+
+impl Etag {
+    /// Computes the entity tag of the given raw item contents.
+    pub fn from_contents(contents: impl AsRef<[u8]>) -> Self {
+        Self(Sha1::from(contents).digest().to_string())
+    }
+
+    /// Returns the entity tag as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Etag {
+    fn from(etag: String) -> Self {
+        Self(etag)
+    }
+}
+
+impl From<Etag> for String {
+    fn from(etag: Etag) -> Self {
+        etag.0
+    }
+}
+
+impl AsRef<str> for Etag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Etag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
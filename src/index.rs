@@ -0,0 +1,261 @@
+//! Module dedicated to Vdir collection indexes.
+//!
+//! An index is an optional hidden file stored inside a collection
+//! (see [`crate::constants::INDEX`]), which maps each item's file
+//! name to its etag, UID, format and a few summary fields. Reading
+//! the index is much cheaper than reading and parsing every item of
+//! the collection.
+//!
+//! See [`crate::coroutines::build_index::BuildIndex`],
+//! [`crate::coroutines::refresh_index::RefreshIndex`] and
+//! [`crate::coroutines::query_index::QueryIndex`].
+
+use std::collections::HashMap;
+
+use calcard::{icalendar::ICalendarProperty, vcard::VCardProperty};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    etag::Etag,
+    item::{Item, ItemFormat, ItemKind},
+    jsonl::{self, JsonLinesError},
+};
+
+/// The current version of the index file format.
+pub const INDEX_VERSION: u64 = 1;
+
+/// Errors that can occur while decoding an index.
+#[derive(Clone, Debug, Error)]
+pub enum IndexError {
+    /// The index file does not start with a valid header.
+    #[error("Missing or invalid index header")]
+    InvalidHeader,
+
+    /// The index file has been written by an unsupported version of
+    /// the format.
+    #[error("Unsupported index version {0}")]
+    UnsupportedVersion(u64),
+
+    /// An index line could not be decoded.
+    #[error("Invalid index at line {0}: {1}")]
+    InvalidLine(usize, String),
+}
+
+impl From<JsonLinesError> for IndexError {
+    fn from(err: JsonLinesError) -> Self {
+        match err {
+            JsonLinesError::InvalidHeader => Self::InvalidHeader,
+            JsonLinesError::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+            JsonLinesError::InvalidLine(n, err) => Self::InvalidLine(n, err),
+        }
+    }
+}
+
+/// The index of a Vdir collection.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Index {
+    /// The index entries, indexed by item href (file name).
+    pub entries: HashMap<String, IndexEntry>,
+}
+
+impl Index {
+    /// Decodes an index from the given raw contents.
+    ///
+    /// The index file format is JSON lines (UTF-8). The first line
+    /// is a header containing the format version, each following
+    /// line describes one item:
+    ///
+    /// ```text
+    /// {"version":1}
+    /// {"href":"uid.vcf","etag":"…","format":"vcard","uid":"uid","name":"John Doe","emails":["john@example.org"]}
+    /// ```
+    ///
+    /// Empty lines are ignored.
+    pub fn from_bytes(contents: impl AsRef<[u8]>) -> Result<Self, IndexError> {
+        let lines: Vec<IndexLine> = jsonl::decode(contents.as_ref(), INDEX_VERSION)?;
+
+        let entries = lines
+            .into_iter()
+            .map(|line| {
+                let entry = IndexEntry {
+                    etag: line.etag.into(),
+                    format: match line.format {
+                        IndexFormat::Ical => ItemFormat::Ical,
+                        IndexFormat::Vcard => ItemFormat::Vcard,
+                    },
+                    uid: line.uid,
+                    name: line.name,
+                    emails: line.emails,
+                    start: line.start,
+                };
+
+                (line.href, entry)
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    /// Encodes the index into raw contents.
+    ///
+    /// Entries are sorted by href, so that the same index always
+    /// gives the same contents.
+    ///
+    /// See [`Index::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut hrefs: Vec<_> = self.entries.keys().collect();
+        hrefs.sort();
+
+        let lines = hrefs.into_iter().map(|href| {
+            let entry = &self.entries[href];
+
+            IndexLine {
+                href: href.clone(),
+                etag: entry.etag.to_string(),
+                format: match entry.format {
+                    ItemFormat::Ical => IndexFormat::Ical,
+                    ItemFormat::Vcard => IndexFormat::Vcard,
+                },
+                uid: entry.uid.clone(),
+                name: entry.name.clone(),
+                emails: entry.emails.clone(),
+                start: entry.start,
+            }
+        });
+
+        jsonl::encode(INDEX_VERSION, lines)
+    }
+
+    /// Returns the entries matching the given query, sorted by name
+    /// then by href.
+    ///
+    /// An entry matches when its UID, name or one of its emails
+    /// contains the query, ignoring case. An empty query matches
+    /// all entries.
+    pub fn search(&self, query: &str) -> Vec<(&String, &IndexEntry)> {
+        let query = query.trim().to_lowercase();
+
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.matches(&query))
+            .collect();
+
+        entries.sort_by(|(a_href, a), (b_href, b)| (&a.name, a_href).cmp(&(&b.name, b_href)));
+        entries
+    }
+}
+
+/// The index entry of an item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IndexEntry {
+    /// The entity tag of the item, used to detect changes.
+    pub etag: Etag,
+
+    /// The format of the item.
+    pub format: ItemFormat,
+
+    /// The unique identifier of the item, if any.
+    pub uid: Option<String>,
+
+    /// The name of the item: the formatted name (FN) of vCards, or
+    /// the summary (SUMMARY) of the first iCalendar component having
+    /// one.
+    pub name: Option<String>,
+
+    /// The email addresses (EMAIL) of vCards.
+    pub emails: Vec<String>,
+
+    /// The start (DTSTART) of the first iCalendar component having
+    /// one, as a Unix timestamp.
+    pub start: Option<i64>,
+}
+
+impl IndexEntry {
+    /// Builds the index entry of the given item.
+    ///
+    /// The etag is computed from the item's contents when the item
+    /// does not have one.
+    pub fn from_item(item: &Item) -> Self {
+        let etag = match &item.etag {
+            Some(etag) => etag.clone(),
+            None => Etag::from_contents(item.to_bytes()),
+        };
+
+        let mut entry = Self {
+            etag,
+            format: item.kind.format(),
+            uid: item.kind.uid().map(ToOwned::to_owned),
+            name: None,
+            emails: Vec::new(),
+            start: None,
+        };
+
+        match &item.kind {
+            ItemKind::Vcard(vcard) => {
+                entry.name = vcard
+                    .property(&VCardProperty::Fn)
+                    .and_then(|entry| entry.values.first()?.as_text())
+                    .map(ToOwned::to_owned);
+
+                entry.emails = vcard
+                    .properties(&VCardProperty::Email)
+                    .filter_map(|entry| entry.values.first()?.as_text())
+                    .map(ToOwned::to_owned)
+                    .collect();
+            }
+            ItemKind::Ical(ical) => {
+                entry.name = ical
+                    .components
+                    .iter()
+                    .filter_map(|component| component.property(&ICalendarProperty::Summary))
+                    .find_map(|entry| entry.values.first()?.as_text())
+                    .map(ToOwned::to_owned);
+
+                entry.start = ical
+                    .components
+                    .iter()
+                    .filter_map(|component| component.property(&ICalendarProperty::Dtstart))
+                    .filter_map(|entry| entry.values.first()?.as_partial_date_time())
+                    .find_map(|date| date.to_timestamp());
+            }
+        }
+
+        entry
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        if query.is_empty() {
+            return true;
+        }
+
+        self.uid
+            .iter()
+            .chain(&self.name)
+            .chain(&self.emails)
+            .any(|field| field.to_lowercase().contains(query))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum IndexFormat {
+    Ical,
+    Vcard,
+}
+
+#[derive(Deserialize, Serialize)]
+struct IndexLine {
+    href: String,
+    etag: String,
+    format: IndexFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    emails: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    start: Option<i64>,
+}
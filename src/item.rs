@@ -1,16 +1,22 @@
 //! Module dedicated to the Vdir collection's item.
 
 use std::{
+    fmt,
     hash::{Hash, Hasher},
     path::{Path, PathBuf},
 };
 
-use calcard::{icalendar::ICalendar, vcard::VCard};
+use calcard::{
+    icalendar::{ICalendar, ICalendarComponentType, ICalendarProperty, ICalendarValue},
+    vcard::{VCard, VCardEntry, VCardProperty, VCardValue},
+};
+use sha1_smol::Sha1;
 use uuid::Uuid;
 
 use crate::{
     collection::Collection,
     constants::{ICS, VCF},
+    etag::Etag,
 };
 
 /// The Vdir collection's item.
@@ -25,6 +31,21 @@ pub struct Item {
 
     /// The collection's item kind.
     pub kind: ItemKind,
+
+    /// The entity tag of the collection's item file.
+    ///
+    /// Set when the item is read from the filesystem, `None` when
+    /// the item has not been saved yet. Derived from the item's
+    /// contents, not from file metadata: it is not compatible with
+    /// vdirsyncer entity tags (see [`Etag`]).
+    pub etag: Option<Etag>,
+
+    /// The original contents of the collection's item file.
+    ///
+    /// Set when the item is read from the filesystem. As long as the
+    /// kind is not modified, these contents are written back as they
+    /// are, so that reading then saving an item is lossless.
+    pub raw: Option<Vec<u8>>,
 }
 
 impl Item {
@@ -39,7 +60,57 @@ impl Item {
             .join(Uuid::new_v4().to_string())
             .with_extension(kind.extension());
 
-        Self { path, kind }
+        Self {
+            path,
+            kind,
+            etag: None,
+            raw: None,
+        }
+    }
+
+    /// Creates a new collection's item for the given collection and
+    /// the given kind, named after its UID.
+    ///
+    /// Falls back to an auto-generated UUID when the kind has no
+    /// UID. See [`uid_file_stem`].
+    pub fn named_after_uid(collection: &Collection, kind: ItemKind) -> Item {
+        let mut item = Self::new(collection, kind);
+        item.rename_after_uid();
+        item
+    }
+
+    /// Returns `true` if the kind differs from the original contents
+    /// of the item file.
+    ///
+    /// Items that have not been read from the filesystem are always
+    /// considered modified.
+    pub fn is_modified(&self) -> bool {
+        match &self.raw {
+            Some(raw) => ItemKind::parse(self.kind.format(), raw).as_ref() != Some(&self.kind),
+            None => true,
+        }
+    }
+
+    /// Returns the contents to write into the item file.
+    ///
+    /// The original contents are returned unchanged if the kind has
+    /// not been modified, otherwise the kind is serialized.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match &self.raw {
+            Some(raw) if !self.is_modified() => raw.clone(),
+            _ => self.kind.to_string().into_bytes(),
+        }
+    }
+
+    /// Renames the item after its UID, within the same directory.
+    ///
+    /// The path is left untouched when the item has no UID. See
+    /// [`uid_file_stem`].
+    pub fn rename_after_uid(&mut self) {
+        if let Some(uid) = self.kind.uid() {
+            let name = format!("{}.{}", uid_file_stem(uid), self.kind.extension());
+            self.path.set_file_name(name);
+        }
     }
 }
 
@@ -55,13 +126,47 @@ impl AsRef<Path> for Item {
     }
 }
 
+#[allow(clippy::to_string_trait_impl)]
 impl ToString for Item {
     fn to_string(&self) -> String {
-        match &self.kind {
-            ItemKind::Ical(ical) => ical.to_string(),
-            ItemKind::Vcard(vcard) => vcard.to_string(),
+        self.kind.to_string()
+    }
+}
+
+/// The maximum length of an item file stem derived from a UID.
+///
+/// See [`uid_file_stem`].
+pub const MAX_UID_FILE_STEM_LEN: usize = 128;
+
+/// Derives an item file stem from the given unique identifier (UID).
+///
+/// As recommended by the Vdir standard, items are named after their
+/// UID. Characters other than ASCII alphanumerics, `-`, `_`, `.` and
+/// `+` are percent-encoded, as well as a leading `.` that would hide
+/// the file, so that a UID always maps to the same safe file name.
+///
+/// Since percent-encoding can triple the length of a UID, stems
+/// longer than [`MAX_UID_FILE_STEM_LEN`] are replaced by the SHA-1
+/// hex digest of the UID, so that file names stay below the usual
+/// filesystem limit (255 bytes), temporary files included.
+pub fn uid_file_stem(uid: &str) -> String {
+    let mut stem = String::with_capacity(uid.len());
+
+    for (i, byte) in uid.bytes().enumerate() {
+        let safe = byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'+' | b'.');
+
+        if safe && !(i == 0 && byte == b'.') {
+            stem.push(byte as char);
+        } else {
+            stem.push_str(&format!("%{byte:02X}"));
         }
     }
+
+    if stem.len() > MAX_UID_FILE_STEM_LEN {
+        return Sha1::from(uid).digest().to_string();
+    }
+
+    stem
 }
 
 /// The Vdir collection's item's kind.
@@ -82,11 +187,233 @@ pub enum ItemKind {
 }
 
 impl ItemKind {
+    /// Parses the given contents using the given format.
+    fn parse(format: ItemFormat, contents: &[u8]) -> Option<Self> {
+        let contents = std::str::from_utf8(contents).ok()?;
+
+        match format {
+            ItemFormat::Ical => ICalendar::parse(contents).ok().map(Self::Ical),
+            ItemFormat::Vcard => VCard::parse(contents).ok().map(Self::Vcard),
+        }
+    }
+
     /// Returns the file extension associated to the item's kind.
+    pub fn extension(&self) -> &'static str {
+        self.format().extension()
+    }
+
+    /// Returns the unique identifier (UID) of the item's kind.
+    ///
+    /// For iCalendar items, returns the UID of the first component
+    /// having one.
+    pub fn uid(&self) -> Option<&str> {
+        let uid = match self {
+            Self::Ical(ical) => ical.uids().next(),
+            Self::Vcard(vcard) => vcard.uid(),
+        };
+
+        uid.map(str::trim).filter(|uid| !uid.is_empty())
+    }
+
+    /// Replaces the unique identifier (UID) of the item's kind.
+    ///
+    /// For iCalendar items, the UID of every component having one is
+    /// replaced, so that recurrence overrides stay attached to their
+    /// main component. Events, tasks and journal entries without UID
+    /// get the given one.
+    pub fn set_uid(&mut self, uid: impl ToString) {
+        let uid = uid.to_string();
+
+        match self {
+            Self::Ical(ical) => {
+                for component in &mut ical.components {
+                    if let Some(entry) = component.property_mut(&ICalendarProperty::Uid) {
+                        entry.values = vec![ICalendarValue::Text(uid.clone())];
+                        continue;
+                    }
+
+                    if has_uid(&component.component_type) {
+                        component.add_uid(&uid);
+                    }
+                }
+            }
+            Self::Vcard(vcard) => {
+                let entry = vcard
+                    .entries
+                    .iter_mut()
+                    .find(|entry| entry.name == VCardProperty::Uid);
+
+                match entry {
+                    Some(entry) => entry.values = vec![VCardValue::Text(uid)],
+                    None => {
+                        let entry = VCardEntry::new(VCardProperty::Uid).with_value(uid);
+                        vcard.entries.push(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Assigns a UID to the item's kind if it lacks one.
+    ///
+    /// vCards without UID get a generated one. Events, tasks and
+    /// journal entries of iCalendar items without UID get the UID of
+    /// the item if any (see [`ItemKind::uid`]), a generated one
+    /// otherwise, so that all the components of an item share the
+    /// same UID.
+    ///
+    /// Returns `true` if a UID has been assigned.
+    pub fn ensure_uid(&mut self) -> bool {
+        let uid = match self.uid() {
+            Some(uid) => uid.to_owned(),
+            None => Uuid::new_v4().to_string(),
+        };
+
+        match self {
+            Self::Ical(ical) => {
+                let mut assigned = false;
+
+                for component in &mut ical.components {
+                    if !has_uid(&component.component_type) {
+                        continue;
+                    }
+
+                    if component.uid().is_some_and(|uid| !uid.trim().is_empty()) {
+                        continue;
+                    }
+
+                    component
+                        .entries
+                        .retain(|entry| entry.name != ICalendarProperty::Uid);
+                    component.add_uid(&uid);
+                    assigned = true;
+                }
+
+                assigned
+            }
+            Self::Vcard(vcard) => {
+                if vcard.uid().is_some_and(|uid| !uid.trim().is_empty()) {
+                    return false;
+                }
+
+                self.set_uid(uid);
+                true
+            }
+        }
+    }
+
+    /// Returns the last modification date of the item's kind, as a
+    /// UNIX timestamp.
+    ///
+    /// Relies on the LAST-MODIFIED property for iCalendar items (the
+    /// most recent one among components), and on the REV property
+    /// for vCard items.
+    pub fn last_modified(&self) -> Option<i64> {
+        match self {
+            Self::Ical(ical) => ical
+                .components
+                .iter()
+                .filter_map(|component| component.property(&ICalendarProperty::LastModified))
+                .filter_map(|entry| entry.values.first()?.as_partial_date_time())
+                .filter_map(|date| date.to_timestamp())
+                .max(),
+            Self::Vcard(vcard) => vcard
+                .property(&VCardProperty::Rev)?
+                .values
+                .first()?
+                .as_partial_date_time()?
+                .to_timestamp(),
+        }
+    }
+
+    /// Returns the format of the item's kind.
+    pub fn format(&self) -> ItemFormat {
+        match self {
+            Self::Ical(_) => ItemFormat::Ical,
+            Self::Vcard(_) => ItemFormat::Vcard,
+        }
+    }
+}
+
+impl fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ical(ical) => write!(f, "{ical}"),
+            Self::Vcard(vcard) => write!(f, "{vcard}"),
+        }
+    }
+}
+
+/// Returns `true` if components of the given type are identified by
+/// a UID.
+fn has_uid(component_type: &ICalendarComponentType) -> bool {
+    matches!(
+        component_type,
+        ICalendarComponentType::VEvent
+            | ICalendarComponentType::VTodo
+            | ICalendarComponentType::VJournal
+    )
+}
+
+/// The Vdir collection's item's format.
+///
+/// Same as [`ItemKind`], without the parsed contents. The format of
+/// an item can be guessed from its file extension.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ItemFormat {
+    /// The iCalendar format (.ics).
+    Ical,
+
+    /// The vCard format (.vcf).
+    Vcard,
+}
+
+impl ItemFormat {
+    /// Guesses the item's format from the given path's file
+    /// extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let ext = path.as_ref().extension()?;
+
+        if ext == ICS {
+            Some(Self::Ical)
+        } else if ext == VCF {
+            Some(Self::Vcard)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the file extension associated to the item's format.
     pub fn extension(&self) -> &'static str {
         match self {
-            Self::Ical(_) => ICS,
-            Self::Vcard(_) => VCF,
+            Self::Ical => ICS,
+            Self::Vcard => VCF,
         }
     }
 }
+
+/// The Vdir collection's item's reference.
+///
+/// Represents an item whose contents have not been parsed.
+///
+/// See [`crate::coroutines::list_item_hrefs::ListItemHrefs`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ItemHref {
+    /// The file path of the collection's item.
+    pub path: PathBuf,
+
+    /// The format of the collection's item.
+    pub format: ItemFormat,
+
+    /// The entity tag of the collection's item file, if computed.
+    ///
+    /// Derived from the item's contents, not from file metadata (see
+    /// [`Etag`]).
+    pub etag: Option<Etag>,
+}
+
+impl AsRef<Path> for ItemHref {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
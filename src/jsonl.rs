@@ -0,0 +1,84 @@
+//! Module dedicated to versioned JSON lines files.
+//!
+//! Used by the files managed by the crate itself, like the
+//! synchronization status (see [`crate::sync::SyncStatus`]) and the
+//! collection index (see [`crate::index::Index`]). The first line is
+//! a header containing the format version, each following line is a
+//! JSON-encoded entry:
+//!
+//! ```text
+//! {"version":1}
+//! {…}
+//! ```
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Errors that can occur while decoding a JSON lines file.
+#[derive(Clone, Debug)]
+pub(crate) enum JsonLinesError {
+    /// The file does not start with a valid header.
+    InvalidHeader,
+
+    /// The file has been written by an unsupported version of the
+    /// format.
+    UnsupportedVersion(u64),
+
+    /// A line could not be decoded, with its number (starting at 1)
+    /// and the decoding error.
+    InvalidLine(usize, String),
+}
+
+#[derive(Deserialize, Serialize)]
+struct Header {
+    version: u64,
+}
+
+/// Decodes the entries of the given raw contents, which must have
+/// been written by the given version of the format.
+///
+/// Empty contents decode to no entries, and empty lines are
+/// ignored.
+pub(crate) fn decode<T: DeserializeOwned>(
+    contents: &[u8],
+    version: u64,
+) -> Result<Vec<T>, JsonLinesError> {
+    let contents = String::from_utf8_lossy(contents);
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+
+    let Ok(header) = serde_json::from_str::<Header>(header) else {
+        return Err(JsonLinesError::InvalidHeader);
+    };
+
+    if header.version != version {
+        return Err(JsonLinesError::UnsupportedVersion(header.version));
+    }
+
+    lines
+        .map(|(n, line)| {
+            serde_json::from_str(line)
+                .map_err(|err| JsonLinesError::InvalidLine(n + 1, err.to_string()))
+        })
+        .collect()
+}
+
+/// Encodes the given entries into raw contents, using the given
+/// version of the format.
+pub(crate) fn encode<T: Serialize>(version: u64, entries: impl IntoIterator<Item = T>) -> Vec<u8> {
+    // NOTE: serializing plain structs of strings cannot fail
+    let mut contents = serde_json::to_string(&Header { version }).unwrap_or_default();
+    contents.push('\n');
+
+    for entry in entries {
+        contents.push_str(&serde_json::to_string(&entry).unwrap_or_default());
+        contents.push('\n');
+    }
+
+    contents.into_bytes()
+}
@@ -2,6 +2,19 @@
 #![doc = include_str!("../README.md")]
 
 pub mod collection;
+pub mod conflict;
 pub mod constants;
 pub mod coroutines;
+pub mod diff;
+pub mod etag;
+pub mod index;
 pub mod item;
+mod jsonl;
+pub mod lookup;
+pub mod merge;
+pub mod query;
+pub mod runtime;
+pub mod search;
+pub mod storage;
+pub mod sync;
+pub mod tmp;
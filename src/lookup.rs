@@ -0,0 +1,169 @@
+//! Module dedicated to email addresses lookup.
+//!
+//! Mail clients like mutt (`query_command`) or aerc
+//! (`address-book-cmd`) complete email addresses using an external
+//! command, whose output contains one contact per line, made of the
+//! email address, the name and extra information, separated by tabs:
+//!
+//! ```text
+//! john@example.org<TAB>John Doe<TAB>work
+//! ```
+//!
+//! See [`crate::coroutines::lookup_emails::LookupEmails`].
+
+use std::fmt;
+
+use crate::{
+    item::{Item, ItemKind},
+    query::{components, split_escaped, unescape},
+};
+
+/// An email address of a contact.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct EmailContact {
+    /// The email address.
+    pub email: String,
+
+    /// The name of the contact, from its formatted name (FN) or
+    /// from its structured name (N).
+    pub name: String,
+
+    /// Extra information about the address: the values of its TYPE
+    /// parameter, comma-separated.
+    pub extra: String,
+}
+
+impl EmailContact {
+    /// Returns the email addresses of the given item matching the
+    /// given query.
+    ///
+    /// The query is matched against the formatted name (FN), the
+    /// structured name (N), the nicknames (NICKNAME) and the email
+    /// addresses (EMAIL) of vCards, ignoring case. When the name or
+    /// a nickname matches, all the email addresses of the contact
+    /// are returned, otherwise only the matching ones. An empty
+    /// query matches all the email addresses.
+    pub fn from_item(item: &Item, query: &str) -> Vec<Self> {
+        if !matches!(item.kind, ItemKind::Vcard(_)) {
+            return Vec::new();
+        }
+
+        let query = query.trim().to_lowercase();
+        let matches = |value: &str| value.to_lowercase().contains(&query);
+
+        let mut full_name = None;
+        let mut structured_name = None;
+        let mut names_match = false;
+        let mut emails = Vec::new();
+
+        for property in components(&item.kind)
+            .into_iter()
+            .flat_map(|component| component.properties)
+        {
+            match property.name.to_ascii_uppercase().as_str() {
+                "FN" => {
+                    names_match |= matches(&property.value);
+                    full_name.get_or_insert(property.value);
+                }
+                "N" => {
+                    let name = structured_name_to_string(&property.raw_value);
+                    names_match |= matches(&name);
+                    structured_name.get_or_insert(name);
+                }
+                "NICKNAME" => {
+                    names_match |= split_escaped(&property.raw_value, ',')
+                        .into_iter()
+                        .any(|nickname| matches(&unescape(nickname)));
+                }
+                "EMAIL" => {
+                    let extra = property
+                        .params
+                        .into_iter()
+                        .filter(|(name, _)| name.eq_ignore_ascii_case("TYPE"))
+                        .map(|(_, value)| value.to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(",");
+
+                    emails.push((property.value, extra));
+                }
+                _ => (),
+            }
+        }
+
+        let name = full_name
+            .filter(|name| !name.trim().is_empty())
+            .or(structured_name)
+            .unwrap_or_default();
+
+        emails
+            .into_iter()
+            .filter(|(email, _)| !email.trim().is_empty())
+            .filter(|(email, _)| names_match || matches(email))
+            .map(|(email, extra)| Self {
+                email: email.trim().to_owned(),
+                name: name.trim().to_owned(),
+                extra,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for EmailContact {
+    /// Formats the contact as a mutt/aerc query line, without line
+    /// ending.
+    ///
+    /// Tabs and line breaks are replaced by spaces, so that they
+    /// cannot break the line format.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clean = |s: &str| s.replace(['\t', '\r', '\n'], " ");
+
+        write!(
+            f,
+            "{}\t{}\t{}",
+            clean(&self.email),
+            clean(&self.name),
+            clean(&self.extra)
+        )
+    }
+}
+
+/// Formats the given contacts as a mutt `query_command` output.
+///
+/// mutt ignores the first line of the output, which is used as a
+/// status message. Other lines contain one contact each, see
+/// [`EmailContact`]'s [`fmt::Display`] implementation, which can be
+/// used as is for aerc's `address-book-cmd`.
+pub fn format_mutt(contacts: &[EmailContact]) -> String {
+    let mut out = match contacts.len() {
+        1 => String::from("1 matching email address\n"),
+        n => format!("{n} matching email addresses\n"),
+    };
+
+    for contact in contacts {
+        out.push_str(&contact.to_string());
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Builds a displayable name from a structured name (N) value, made
+/// of the family names, given names, additional names, honorific
+/// prefixes and honorific suffixes.
+///
+/// The value is expected to be escaped, so that escaped separators
+/// do not split components.
+fn structured_name_to_string(value: &str) -> String {
+    let parts = split_escaped(value, ';');
+    let part = |i: usize| {
+        let part = parts.get(i).copied().unwrap_or_default();
+        let values: Vec<_> = split_escaped(part, ',').into_iter().map(unescape).collect();
+        values.join(" ")
+    };
+
+    [part(3), part(1), part(2), part(0), part(4)]
+        .into_iter()
+        .filter(|part| !part.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,303 @@
+//! Module dedicated to the three-way merge of items.
+
+use calcard::{
+    icalendar::{ICalendar, ICalendarComponent, ICalendarEntry, ICalendarProperty},
+    vcard::{VCard, VCardEntry, VCardProperty},
+};
+use thiserror::Error;
+
+use crate::{diff::group_entries, item::ItemKind};
+
+/// Errors that can occur during the merge.
+#[derive(Clone, Debug, Error)]
+pub enum MergeError {
+    /// The items do not share the same format.
+    #[error("Cannot merge items of different formats")]
+    FormatMismatch,
+
+    /// The iCalendar items do not share the same components, and
+    /// cannot be merged property by property.
+    #[error("Cannot merge iCalendar items with different components")]
+    ComponentsMismatch,
+
+    /// Some properties have been modified differently on both sides.
+    #[error("Cannot merge items with {} conflicting properties", .0.len())]
+    Conflicts(Vec<MergeConflict>),
+}
+
+/// A property modified differently on both sides.
+///
+/// Each side holds all the entries of the property, in their order
+/// of appearance. An empty side means that the property is absent.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MergeConflict {
+    /// The vCard property variant.
+    Vcard {
+        /// The group of the property.
+        group: Option<String>,
+
+        /// The name of the property.
+        name: VCardProperty,
+
+        /// The property entries of the common ancestor.
+        base: Vec<VCardEntry>,
+
+        /// The property entries of the local side.
+        local: Vec<VCardEntry>,
+
+        /// The property entries of the remote side.
+        remote: Vec<VCardEntry>,
+    },
+
+    /// The iCalendar property variant.
+    Ical {
+        /// The index of the component owning the property.
+        component: usize,
+
+        /// The name of the property.
+        name: ICalendarProperty,
+
+        /// The property entries of the common ancestor.
+        base: Vec<ICalendarEntry>,
+
+        /// The property entries of the local side.
+        local: Vec<ICalendarEntry>,
+
+        /// The property entries of the remote side.
+        remote: Vec<ICalendarEntry>,
+    },
+}
+
+/// Merges the local and remote versions of an item, given their
+/// common ancestor.
+///
+/// Items are merged property by property: a property modified on
+/// one side only is taken from that side, while a property modified
+/// differently on both sides is reported as a [`MergeConflict`].
+/// Properties are compared as a whole, including all their entries
+/// (for example all the TEL entries of a vCard).
+///
+/// Properties updated by clients on every modification (REV for
+/// vCard, DTSTAMP, LAST-MODIFIED and SEQUENCE for iCalendar) never
+/// conflict: the most recent value wins.
+///
+/// iCalendar items are merged component by component, which requires
+/// all versions to share the same component layout. Otherwise items
+/// can only be merged when one side did not change.
+pub fn merge(base: &ItemKind, local: &ItemKind, remote: &ItemKind) -> Result<ItemKind, MergeError> {
+    if local == remote || remote == base {
+        return Ok(local.clone());
+    }
+
+    if local == base {
+        return Ok(remote.clone());
+    }
+
+    match (base, local, remote) {
+        (ItemKind::Vcard(base), ItemKind::Vcard(local), ItemKind::Vcard(remote)) => {
+            merge_vcard(base, local, remote).map(ItemKind::Vcard)
+        }
+        (ItemKind::Ical(base), ItemKind::Ical(local), ItemKind::Ical(remote)) => {
+            merge_ical(base, local, remote).map(ItemKind::Ical)
+        }
+        _ => Err(MergeError::FormatMismatch),
+    }
+}
+
+fn merge_vcard(base: &VCard, local: &VCard, remote: &VCard) -> Result<VCard, MergeError> {
+    let key = |entry: &VCardEntry| (entry.group.clone(), entry.name.clone());
+
+    let newest = |(_, name): &(Option<String>, VCardProperty),
+                  local: &[VCardEntry],
+                  remote: &[VCardEntry]| {
+        if *name != VCardProperty::Rev {
+            return None;
+        }
+
+        let rev = |entries: &[VCardEntry]| {
+            entries
+                .first()
+                .and_then(|entry| entry.values.first())
+                .and_then(|value| value.as_partial_date_time())
+                .cloned()
+        };
+
+        if rev(local) >= rev(remote) {
+            Some(local.to_vec())
+        } else {
+            Some(remote.to_vec())
+        }
+    };
+
+    let (entries, conflicts) =
+        merge_entries(&base.entries, &local.entries, &remote.entries, key, newest);
+
+    if !conflicts.is_empty() {
+        let conflicts = conflicts
+            .into_iter()
+            .map(
+                |((group, name), base, local, remote)| MergeConflict::Vcard {
+                    group,
+                    name,
+                    base,
+                    local,
+                    remote,
+                },
+            )
+            .collect();
+
+        return Err(MergeError::Conflicts(conflicts));
+    }
+
+    Ok(VCard { entries })
+}
+
+fn merge_ical(
+    base: &ICalendar,
+    local: &ICalendar,
+    remote: &ICalendar,
+) -> Result<ICalendar, MergeError> {
+    let same_layout = |a: &ICalendar, b: &ICalendar| {
+        a.components.len() == b.components.len()
+            && a.components.iter().zip(&b.components).all(|(a, b)| {
+                a.component_type == b.component_type && a.component_ids == b.component_ids
+            })
+    };
+
+    if !same_layout(base, local) || !same_layout(base, remote) {
+        return Err(MergeError::ComponentsMismatch);
+    }
+
+    let key = |entry: &ICalendarEntry| entry.name.clone();
+
+    let newest = |name: &ICalendarProperty, local: &[ICalendarEntry], remote: &[ICalendarEntry]| {
+        let value = |entries: &[ICalendarEntry]| {
+            entries
+                .first()
+                .and_then(|entry| entry.values.first())
+                .cloned()
+        };
+
+        let local_is_newest = match name {
+            ICalendarProperty::Dtstamp | ICalendarProperty::LastModified => {
+                let date = |entries| value(entries)?.as_partial_date_time().cloned();
+                date(local) >= date(remote)
+            }
+            ICalendarProperty::Sequence => {
+                let sequence = |entries| value(entries)?.as_integer();
+                sequence(local) >= sequence(remote)
+            }
+            _ => return None,
+        };
+
+        if local_is_newest {
+            Some(local.to_vec())
+        } else {
+            Some(remote.to_vec())
+        }
+    };
+
+    let mut components = Vec::with_capacity(local.components.len());
+    let mut conflicts = Vec::new();
+
+    for (index, ((base, local), remote)) in base
+        .components
+        .iter()
+        .zip(&local.components)
+        .zip(&remote.components)
+        .enumerate()
+    {
+        let (entries, component_conflicts) =
+            merge_entries(&base.entries, &local.entries, &remote.entries, key, newest);
+
+        conflicts.extend(
+            component_conflicts
+                .into_iter()
+                .map(|(name, base, local, remote)| MergeConflict::Ical {
+                    component: index,
+                    name,
+                    base,
+                    local,
+                    remote,
+                }),
+        );
+
+        components.push(ICalendarComponent {
+            component_type: local.component_type.clone(),
+            entries,
+            component_ids: local.component_ids.clone(),
+        });
+    }
+
+    if !conflicts.is_empty() {
+        return Err(MergeError::Conflicts(conflicts));
+    }
+
+    Ok(ICalendar { components })
+}
+
+type EntriesConflict<K, T> = (K, Vec<T>, Vec<T>, Vec<T>);
+
+/// Merges entries grouped by the given key.
+///
+/// Merged entries keep the order of the local side, followed by the
+/// entries only found on the remote side. Conflicting groups are
+/// given to the `newest` function, which can pick one side.
+fn merge_entries<T, K>(
+    base: &[T],
+    local: &[T],
+    remote: &[T],
+    key: impl Fn(&T) -> K,
+    newest: impl Fn(&K, &[T], &[T]) -> Option<Vec<T>>,
+) -> (Vec<T>, Vec<EntriesConflict<K, T>>)
+where
+    T: Clone + PartialEq,
+    K: Clone + PartialEq,
+{
+    let base = group_entries(base, &key);
+    let local = group_entries(local, &key);
+    let remote = group_entries(remote, &key);
+
+    let mut keys: Vec<&K> = local.iter().map(|(key, _)| key).collect();
+
+    for (key, _) in &remote {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    for (key, _) in &base {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    let find = |groups: &[(K, Vec<T>)], key: &K| -> Vec<T> {
+        groups
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, entries)| entries.clone())
+            .unwrap_or_default()
+    };
+
+    let mut entries = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for key in keys {
+        let b = find(&base, key);
+        let l = find(&local, key);
+        let r = find(&remote, key);
+
+        if l == r || r == b {
+            entries.extend(l);
+        } else if l == b {
+            entries.extend(r);
+        } else if let Some(newest) = newest(key, &l, &r) {
+            entries.extend(newest);
+        } else {
+            conflicts.push((key.clone(), b, l, r));
+        }
+    }
+
+    (entries, conflicts)
+}
@@ -0,0 +1,655 @@
+//! Module dedicated to item filters.
+//!
+//! Filters are modelled on CardDAV `addressbook-query` (RFC 6352)
+//! and CalDAV `calendar-query` (RFC 4791) filters: they match items
+//! by property name, parameter and text, and can be combined with
+//! negation, conjunction and disjunction.
+//!
+//! Filters can be built programmatically, or parsed from an
+//! expression:
+//!
+//! ```text
+//! expr      = or
+//! or        = and *("or" and)
+//! and       = unary *("and" unary)
+//! unary     = "not" unary / "(" expr ")" / predicate
+//! predicate = [component "."] property [";" param [op value]] [op value]
+//! op        = "=" / "~" / "^" / "$" / "==" / "~~" / "^^" / "$$"
+//! value     = word / quoted-string
+//! ```
+//!
+//! The `=`, `~`, `^` and `$` operators respectively match values
+//! equal to, containing, starting with and ending with the given
+//! text, ignoring case. Doubled operators match case-sensitively.
+//! For example, `FN ~ doe and not EMAIL;TYPE=work` matches contacts
+//! whose name contains "doe" and having no work email, whereas
+//! `VEVENT.SUMMARY ^ "team meeting"` matches items having an event
+//! whose summary starts with "team meeting".
+
+use std::{fmt::Write, iter::Peekable, str::Chars, str::FromStr};
+
+use calcard::vcard::VCardVersion;
+use thiserror::Error;
+
+use crate::item::ItemKind;
+
+/// Errors related to filter expressions parsing.
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
+pub enum FilterParseError {
+    /// The expression ended while more tokens were expected.
+    #[error("Unexpected end of filter expression")]
+    UnexpectedEnd,
+
+    /// The expression contains an unexpected token.
+    #[error("Unexpected token {0:?} in filter expression")]
+    UnexpectedToken(String),
+
+    /// The expression contains a string missing its closing quote.
+    #[error("Unterminated string in filter expression")]
+    UnterminatedString,
+}
+
+/// An item filter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Filter {
+    /// Matches items having a property matching the given filter.
+    Property(PropertyFilter),
+
+    /// Matches items not matching the given filter.
+    Not(Box<Filter>),
+
+    /// Matches items matching all the given filters.
+    And(Vec<Filter>),
+
+    /// Matches items matching at least one of the given filters.
+    Or(Vec<Filter>),
+}
+
+impl Filter {
+    /// Builds a filter matching items having the given property.
+    pub fn property(name: impl ToString) -> Self {
+        Self::Property(PropertyFilter::new(name))
+    }
+
+    /// Builds a filter matching items not matching the given filter.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(filter: Filter) -> Self {
+        Self::Not(Box::new(filter))
+    }
+
+    /// Returns `true` if the given item matches the filter.
+    pub fn matches(&self, kind: &ItemKind) -> bool {
+        self.eval(&components(kind))
+    }
+
+    fn eval(&self, components: &[Component]) -> bool {
+        match self {
+            Self::Property(filter) => filter.eval(components),
+            Self::Not(filter) => !filter.eval(components),
+            Self::And(filters) => filters.iter().all(|f| f.eval(components)),
+            Self::Or(filters) => filters.iter().any(|f| f.eval(components)),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterParseError;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(expr)?.into_iter().peekable(),
+        };
+
+        let filter = parser.or()?;
+
+        match parser.tokens.next() {
+            None => Ok(filter),
+            Some(token) => Err(FilterParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+}
+
+/// A property filter.
+///
+/// Matches items having at least one property with the given name
+/// whose parameter and value match the given filters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyFilter {
+    /// The type of the iCalendar component the property belongs to,
+    /// for example `VEVENT`.
+    ///
+    /// When defined, vCards never match.
+    pub component: Option<String>,
+
+    /// The property name, for example `FN` or `SUMMARY`.
+    pub name: String,
+
+    /// The optional parameter filter.
+    pub param: Option<ParamFilter>,
+
+    /// The optional value filter.
+    pub text_match: Option<TextMatch>,
+}
+
+impl PropertyFilter {
+    /// Creates a new filter matching properties with the given name.
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            component: None,
+            name: name.to_string(),
+            param: None,
+            text_match: None,
+        }
+    }
+
+    /// Restricts the filter to the given iCalendar component type.
+    pub fn with_component(mut self, component: impl ToString) -> Self {
+        self.component = Some(component.to_string());
+        self
+    }
+
+    /// Restricts the filter to properties matching the given
+    /// parameter filter.
+    pub fn with_param(mut self, param: ParamFilter) -> Self {
+        self.param = Some(param);
+        self
+    }
+
+    /// Restricts the filter to properties whose value matches the
+    /// given text.
+    pub fn with_text_match(mut self, text_match: TextMatch) -> Self {
+        self.text_match = Some(text_match);
+        self
+    }
+
+    fn eval(&self, components: &[Component]) -> bool {
+        components
+            .iter()
+            .filter(|component| match &self.component {
+                Some(name) => component.name.eq_ignore_ascii_case(name),
+                None => true,
+            })
+            .flat_map(|component| &component.properties)
+            .filter(|property| property.name.eq_ignore_ascii_case(&self.name))
+            .any(|property| {
+                let param = match &self.param {
+                    Some(param) => param.eval(property),
+                    None => true,
+                };
+
+                let value = match &self.text_match {
+                    Some(text_match) => text_match.matches(&property.value),
+                    None => true,
+                };
+
+                param && value
+            })
+    }
+}
+
+impl From<PropertyFilter> for Filter {
+    fn from(filter: PropertyFilter) -> Self {
+        Self::Property(filter)
+    }
+}
+
+/// A parameter filter.
+///
+/// Matches properties having a parameter with the given name whose
+/// value matches the given text. Multi-valued parameters match when
+/// one of their values matches.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParamFilter {
+    /// The parameter name, for example `TYPE`.
+    pub name: String,
+
+    /// The optional value filter.
+    pub text_match: Option<TextMatch>,
+}
+
+impl ParamFilter {
+    /// Creates a new filter matching parameters with the given name.
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            text_match: None,
+        }
+    }
+
+    /// Restricts the filter to parameters whose value matches the
+    /// given text.
+    pub fn with_text_match(mut self, text_match: TextMatch) -> Self {
+        self.text_match = Some(text_match);
+        self
+    }
+
+    fn eval(&self, property: &Property) -> bool {
+        property
+            .params
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(&self.name))
+            .any(|(_, value)| match &self.text_match {
+                Some(text_match) => {
+                    text_match.matches(value) || value.split(',').any(|v| text_match.matches(v))
+                }
+                None => true,
+            })
+    }
+}
+
+/// A text match.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextMatch {
+    /// The text to match.
+    pub text: String,
+
+    /// How the text should match.
+    pub match_type: MatchType,
+
+    /// Whether the match is case-sensitive.
+    ///
+    /// Case-insensitive matches fold both texts to lowercase, like
+    /// the `i;unicode-casemap` collation.
+    pub case_sensitive: bool,
+}
+
+impl TextMatch {
+    /// Creates a new case-insensitive text match.
+    pub fn new(match_type: MatchType, text: impl ToString) -> Self {
+        Self {
+            text: text.to_string(),
+            match_type,
+            case_sensitive: false,
+        }
+    }
+
+    /// Makes the text match case-sensitive or not.
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Returns `true` if the given value matches the text.
+    pub fn matches(&self, value: &str) -> bool {
+        if self.case_sensitive {
+            self.match_type.matches(value, &self.text)
+        } else {
+            self.match_type
+                .matches(&value.to_lowercase(), &self.text.to_lowercase())
+        }
+    }
+}
+
+/// The way a [`TextMatch`] compares texts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MatchType {
+    /// The value equals the text.
+    Equals,
+
+    /// The value contains the text.
+    #[default]
+    Contains,
+
+    /// The value starts with the text.
+    StartsWith,
+
+    /// The value ends with the text.
+    EndsWith,
+}
+
+impl MatchType {
+    fn matches(&self, value: &str, text: &str) -> bool {
+        match self {
+            Self::Equals => value == text,
+            Self::Contains => value.contains(text),
+            Self::StartsWith => value.starts_with(text),
+            Self::EndsWith => value.ends_with(text),
+        }
+    }
+}
+
+/// A component and its properties, flattened to text.
+///
+/// vCards are exposed as a single `VCARD` component.
+pub(crate) struct Component {
+    pub(crate) name: String,
+    pub(crate) properties: Vec<Property>,
+}
+
+pub(crate) struct Property {
+    pub(crate) name: String,
+    pub(crate) params: Vec<(String, String)>,
+    pub(crate) value: String,
+    /// The value as written, still escaped, so that structured
+    /// values can be split before being unescaped.
+    pub(crate) raw_value: String,
+}
+
+pub(crate) fn components(kind: &ItemKind) -> Vec<Component> {
+    match kind {
+        ItemKind::Vcard(vcard) => {
+            let is_v4 = vcard.version().unwrap_or_default() == VCardVersion::V4_0;
+
+            let properties = vcard
+                .entries
+                .iter()
+                .filter_map(|entry| {
+                    let mut line = String::new();
+                    entry.write_to(&mut line, is_v4).ok()?;
+                    parse_line(&line)
+                })
+                .collect();
+
+            vec![Component {
+                name: "VCARD".into(),
+                properties,
+            }]
+        }
+        ItemKind::Ical(ical) => ical
+            .components
+            .iter()
+            .map(|component| Component {
+                name: component.component_type.as_str().to_string(),
+                properties: component
+                    .entries
+                    .iter()
+                    .filter_map(|entry| {
+                        let mut line = String::new();
+                        entry.write_to(&mut line).ok()?;
+                        parse_line(&line)
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// Parses a serialized content line into a [`Property`].
+fn parse_line(line: &str) -> Option<Property> {
+    // NOTE: entries are written folded and CRLF-terminated
+    let line = line.replace("\r\n ", "");
+    let line = line.trim_end();
+
+    let mut quoted = false;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut value = None;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..i]);
+                start = i + 1;
+            }
+            ':' if !quoted => {
+                parts.push(&line[start..i]);
+                value = Some(&line[i + 1..]);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let mut parts = parts.into_iter();
+    let name = parts.next()?;
+    let name = name.rsplit('.').next().unwrap_or(name).to_string();
+
+    let params = parts
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (name.to_string(), value.replace('"', ""))
+        })
+        .collect();
+
+    let value = value?;
+
+    Some(Property {
+        name,
+        params,
+        value: unescape(value),
+        raw_value: value.to_string(),
+    })
+}
+
+/// Splits the given escaped value on the given separator, ignoring
+/// escaped separators.
+///
+/// Parts are returned still escaped, see [`unescape`].
+pub(crate) fn split_escaped(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == separator => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+
+    parts.push(&value[start..]);
+    parts
+}
+
+pub(crate) fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Op(MatchType, bool),
+    Semicolon,
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Self::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Word(word) => f.write_str(word),
+            Self::String(string) => write!(f, "{string:?}"),
+            Self::Op(match_type, case_sensitive) => {
+                let op = match match_type {
+                    MatchType::Equals => '=',
+                    MatchType::Contains => '~',
+                    MatchType::StartsWith => '^',
+                    MatchType::EndsWith => '$',
+                };
+
+                f.write_char(op)?;
+
+                if *case_sensitive {
+                    f.write_char(op)?;
+                }
+
+                Ok(())
+            }
+            Self::Semicolon => f.write_char(';'),
+            Self::LParen => f.write_char('('),
+            Self::RParen => f.write_char(')'),
+        }
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, FilterParseError> {
+    let mut chars = expr.chars().peekable();
+    let mut tokens = Vec::new();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            ';' => Token::Semicolon,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '"' | '\'' => Token::String(quoted_string(&mut chars, c)?),
+            '=' | '~' | '^' | '$' => {
+                let match_type = match c {
+                    '=' => MatchType::Equals,
+                    '~' => MatchType::Contains,
+                    '^' => MatchType::StartsWith,
+                    _ => MatchType::EndsWith,
+                };
+
+                let case_sensitive = chars.next_if_eq(&c).is_some();
+                Token::Op(match_type, case_sensitive)
+            }
+            c => {
+                let mut word = String::from(c);
+
+                while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                    word.push(c);
+                }
+
+                Token::Word(word)
+            }
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, ';' | '(' | ')' | '"' | '\'' | '=' | '~' | '^' | '$')
+}
+
+fn quoted_string(chars: &mut Peekable<Chars>, quote: char) -> Result<String, FilterParseError> {
+    let mut string = String::new();
+
+    loop {
+        match chars.next() {
+            None => break Err(FilterParseError::UnterminatedString),
+            Some(c) if c == quote => break Ok(string),
+            Some('\\') => match chars.next() {
+                Some(c) => string.push(c),
+                None => break Err(FilterParseError::UnterminatedString),
+            },
+            Some(c) => string.push(c),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Filter, FilterParseError> {
+        let mut filters = vec![self.and()?];
+
+        while self.tokens.next_if(|t| t.is_keyword("or")).is_some() {
+            filters.push(self.and()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::Or(filters),
+        })
+    }
+
+    fn and(&mut self) -> Result<Filter, FilterParseError> {
+        let mut filters = vec![self.unary()?];
+
+        while self.tokens.next_if(|t| t.is_keyword("and")).is_some() {
+            filters.push(self.unary()?);
+        }
+
+        Ok(match filters.len() {
+            1 => filters.remove(0),
+            _ => Filter::And(filters),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Filter, FilterParseError> {
+        match self.tokens.next() {
+            None => Err(FilterParseError::UnexpectedEnd),
+            Some(token) if token.is_keyword("not") => Ok(Filter::not(self.unary()?)),
+            Some(Token::LParen) => {
+                let filter = self.or()?;
+
+                match self.tokens.next() {
+                    Some(Token::RParen) => Ok(filter),
+                    Some(token) => Err(FilterParseError::UnexpectedToken(token.to_string())),
+                    None => Err(FilterParseError::UnexpectedEnd),
+                }
+            }
+            Some(Token::Word(name)) => self.predicate(name),
+            Some(token) => Err(FilterParseError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn predicate(&mut self, name: String) -> Result<Filter, FilterParseError> {
+        let mut filter = match name.rsplit_once('.') {
+            Some((component, name)) => PropertyFilter::new(name).with_component(component),
+            None => PropertyFilter::new(name),
+        };
+
+        if self.tokens.next_if_eq(&Token::Semicolon).is_some() {
+            let mut param = match self.tokens.next() {
+                Some(Token::Word(name)) => ParamFilter::new(name),
+                Some(token) => return Err(FilterParseError::UnexpectedToken(token.to_string())),
+                None => return Err(FilterParseError::UnexpectedEnd),
+            };
+
+            if let Some(text_match) = self.text_match()? {
+                param = param.with_text_match(text_match);
+            }
+
+            filter = filter.with_param(param);
+        }
+
+        if let Some(text_match) = self.text_match()? {
+            filter = filter.with_text_match(text_match);
+        }
+
+        Ok(filter.into())
+    }
+
+    fn text_match(&mut self) -> Result<Option<TextMatch>, FilterParseError> {
+        let Some(Token::Op(match_type, case_sensitive)) =
+            self.tokens.next_if(|t| matches!(t, Token::Op(..)))
+        else {
+            return Ok(None);
+        };
+
+        let text = match self.tokens.next() {
+            Some(Token::Word(text) | Token::String(text)) => text,
+            Some(token) => return Err(FilterParseError::UnexpectedToken(token.to_string())),
+            None => return Err(FilterParseError::UnexpectedEnd),
+        };
+
+        let text_match = TextMatch::new(match_type, text).with_case_sensitive(case_sensitive);
+        Ok(Some(text_match))
+    }
+}
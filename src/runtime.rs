@@ -0,0 +1,91 @@
+//! Module dedicated to runtime requirements.
+//!
+//! io-fs requests do not carry the type of filesystem entries, and
+//! io-fs runtimes cannot report a failure back to coroutines. Some
+//! coroutines therefore expect a failed read request to be handed
+//! back unprocessed, so that they can skip the entry:
+//!
+//! - [`crate::coroutines::list_collections::ListCollections`] skips
+//!   root entries that cannot be listed, like regular files.
+//!
+//! - [`crate::coroutines::list_items::ListItems`] skips item entries
+//!   that cannot be read, like directories.
+//!
+//! - [`crate::coroutines::update_item::UpdateItem`] and
+//!   [`crate::coroutines::delete_item::DeleteItem`] fail their
+//!   precondition when the current item file cannot be read.
+//!
+//! Runtimes are not aware of this rule: [`HandBack`] implements it
+//! on top of any runtime handler.
+
+use std::{
+    collections::HashSet,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+use io_fs::io::FsIo;
+use log::debug;
+
+/// Runtime handler wrapper handing back failed read requests.
+///
+/// A read request ([`FsIo::ReadDir`], [`FsIo::ReadFile`] or
+/// [`FsIo::ReadFiles`]) that fails because of the type of an entry
+/// or because it does not exist is returned unprocessed instead of
+/// failing. Any other failure is returned as is.
+///
+/// A request is handed back only once in a row: when the coroutine
+/// emits the same request again, it means that it cannot skip it,
+/// and the failure is returned.
+#[derive(Debug)]
+pub struct HandBack<F: FnMut(FsIo) -> io::Result<FsIo>> {
+    handle: F,
+    failed: Option<FsIo>,
+}
+
+impl<F: FnMut(FsIo) -> io::Result<FsIo>> HandBack<F> {
+    /// Wraps the given runtime handler.
+    pub fn new(handle: F) -> Self {
+        Self {
+            handle,
+            failed: None,
+        }
+    }
+
+    /// Processes the given I/O request using the wrapped runtime
+    /// handler.
+    pub fn handle(&mut self, io: FsIo) -> io::Result<FsIo> {
+        let failed = self.failed.take();
+
+        let err = match (self.handle)(io.clone()) {
+            Ok(io) => return Ok(io),
+            Err(err) => err,
+        };
+
+        let skippable = matches!(
+            err.kind(),
+            ErrorKind::NotADirectory | ErrorKind::IsADirectory | ErrorKind::NotFound
+        );
+
+        let Some(paths) = read_paths(&io) else {
+            return Err(err);
+        };
+
+        if !skippable || failed.as_ref().and_then(read_paths) == Some(paths) {
+            return Err(err);
+        }
+
+        debug!("hand back failed read request: {err}");
+        self.failed = Some(io.clone());
+        Ok(io)
+    }
+}
+
+fn read_paths(io: &FsIo) -> Option<HashSet<PathBuf>> {
+    match io {
+        FsIo::ReadDir(Err(path)) => Some(HashSet::from_iter([path.clone()])),
+        FsIo::ReadFile(Err(path)) => Some(HashSet::from_iter([path.clone()])),
+        FsIo::ReadFiles(Err(paths)) => Some(paths.clone()),
+        _ => None,
+    }
+}
@@ -0,0 +1,199 @@
+//! Module dedicated to items full-text search.
+//!
+//! Property values are split into words (runs of alphanumeric
+//! characters), which are compared to the query terms ignoring
+//! case. A term matches a word equal to or starting with it, so
+//! that "dent" finds "Dentist". Items match when all the terms
+//! match at least one of their properties.
+//!
+//! See [`crate::coroutines::search_items::SearchItems`].
+
+use std::ops::Range;
+
+use crate::{item::Item, query::components};
+
+/// Properties that are never searched, because their values are
+/// not meant to be read by humans.
+const SKIPPED_PROPERTIES: &[&str] = &[
+    "ATTACH",
+    "CREATED",
+    "DTSTAMP",
+    "KEY",
+    "LAST-MODIFIED",
+    "LOGO",
+    "PHOTO",
+    "PRODID",
+    "REV",
+    "SEQUENCE",
+    "SOUND",
+    "UID",
+    "VERSION",
+];
+
+/// Properties naming the item, whose matches weigh double.
+const NAME_PROPERTIES: &[&str] = &["FN", "N", "NICKNAME", "ORG", "SUMMARY"];
+
+/// A full-text search query.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchQuery {
+    terms: Vec<String>,
+}
+
+impl SearchQuery {
+    /// Creates a new query from the given text.
+    pub fn new(query: &str) -> Self {
+        let terms = words(query).map(|(_, word)| word).collect();
+        Self { terms }
+    }
+
+    /// Returns `true` if the query does not contain any term.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Searches the given item.
+    ///
+    /// Returns `None` when the query is empty, or when at least one
+    /// of its terms does not match the item.
+    pub fn search(&self, item: Item) -> Option<SearchHit> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut scores = vec![0; self.terms.len()];
+        let mut matches = Vec::new();
+
+        for component in components(&item.kind) {
+            for property in component.properties {
+                let name = property.name.to_ascii_uppercase();
+
+                if SKIPPED_PROPERTIES.contains(&name.as_str()) {
+                    continue;
+                }
+
+                let weight = if NAME_PROPERTIES.contains(&name.as_str()) {
+                    2
+                } else {
+                    1
+                };
+
+                let mut highlights = Vec::new();
+
+                for (range, word) in words(&property.value) {
+                    let mut matched = false;
+
+                    for (term, score) in self.terms.iter().zip(&mut scores) {
+                        let term_score = if word == *term {
+                            2 * weight
+                        } else if word.starts_with(term.as_str()) {
+                            weight
+                        } else {
+                            continue;
+                        };
+
+                        *score = term_score.max(*score);
+                        matched = true;
+                    }
+
+                    if matched {
+                        highlights.push(range);
+                    }
+                }
+
+                if !highlights.is_empty() {
+                    matches.push(SearchMatch {
+                        component: component.name.clone(),
+                        property: name,
+                        value: property.value,
+                        highlights,
+                    });
+                }
+            }
+        }
+
+        if scores.contains(&0) {
+            return None;
+        }
+
+        Some(SearchHit {
+            item,
+            score: scores.into_iter().sum(),
+            matches,
+        })
+    }
+}
+
+/// An item matching a [`SearchQuery`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchHit {
+    /// The matching item.
+    pub item: Item,
+
+    /// The relevance of the item, the higher the better.
+    ///
+    /// Each term scores the best of its matches: words equal to the
+    /// term score more than words starting with it, and matches in
+    /// properties naming the item (FN, N, NICKNAME, ORG, SUMMARY)
+    /// weigh double.
+    pub score: u32,
+
+    /// The matching properties.
+    pub matches: Vec<SearchMatch>,
+}
+
+/// A property matching at least one term of a [`SearchQuery`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchMatch {
+    /// The type of the component the property belongs to, `VCARD`
+    /// for vCards.
+    pub component: String,
+
+    /// The property name.
+    pub property: String,
+
+    /// The property value, unescaped.
+    pub value: String,
+
+    /// The byte ranges of the matching words in the value.
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl SearchMatch {
+    /// Returns the property value with matching words surrounded by
+    /// the given markers.
+    pub fn highlight(&self, start: &str, end: &str) -> String {
+        let mut out = String::with_capacity(self.value.len());
+        let mut last = 0;
+
+        for range in &self.highlights {
+            out.push_str(&self.value[last..range.start]);
+            out.push_str(start);
+            out.push_str(&self.value[range.clone()]);
+            out.push_str(end);
+            last = range.end;
+        }
+
+        out.push_str(&self.value[last..]);
+        out
+    }
+}
+
+/// Splits the given text into lowercase words, with their byte
+/// range.
+fn words(text: &str) -> impl Iterator<Item = (Range<usize>, String)> + '_ {
+    let mut chars = text.char_indices();
+
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = text.len();
+
+        for (i, c) in chars.by_ref() {
+            if !c.is_alphanumeric() {
+                end = i;
+                break;
+            }
+        }
+
+        Some((start..end, text[start..end].to_lowercase()))
+    })
+}
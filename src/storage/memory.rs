@@ -0,0 +1,133 @@
+//! Module dedicated to the in-memory storage.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    collection::Collection,
+    etag::Etag,
+    item::{Item, ItemHref},
+    storage::Storage,
+};
+
+/// Errors that can occur during in-memory storage operations.
+#[derive(Clone, Debug, Error)]
+pub enum MemoryStorageError {
+    /// The collection does not exist.
+    #[error("Cannot find in-memory collection at {0}")]
+    CollectionNotFound(PathBuf),
+
+    /// The item does not exist.
+    #[error("Cannot find in-memory item at {0}")]
+    ItemNotFound(PathBuf),
+
+    /// The item's entity tag does not match the expected one.
+    #[error("In-memory item at {0} has been modified")]
+    PreconditionFailed(PathBuf),
+}
+
+/// The in-memory storage.
+///
+/// Items are kept in memory, with entity tags computed the same way
+/// as the Vdir storage. Mostly useful to test sync and migration
+/// logic.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    collections: HashMap<PathBuf, Collection>,
+    items: HashMap<PathBuf, Item>,
+}
+
+impl MemoryStorage {
+    /// Creates a new empty in-memory storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts the given collection, replacing the existing one
+    /// sharing the same path.
+    pub fn insert_collection(&mut self, collection: Collection) {
+        self.collections.insert(collection.path.clone(), collection);
+    }
+
+    fn item_mut(
+        &mut self,
+        path: &Path,
+        etag: Option<&Etag>,
+    ) -> Result<&mut Item, MemoryStorageError> {
+        let item = self
+            .items
+            .get_mut(path)
+            .ok_or_else(|| MemoryStorageError::ItemNotFound(path.to_owned()))?;
+
+        if etag.is_some() && item.etag.as_ref() != etag {
+            return Err(MemoryStorageError::PreconditionFailed(path.to_owned()));
+        }
+
+        Ok(item)
+    }
+}
+
+impl Storage for MemoryStorage {
+    type Error = MemoryStorageError;
+
+    fn list_collections(&mut self) -> Result<HashSet<Collection>, Self::Error> {
+        Ok(self.collections.values().cloned().collect())
+    }
+
+    fn list_hrefs(&mut self, collection: &Path) -> Result<HashSet<ItemHref>, Self::Error> {
+        if !self.collections.contains_key(collection) {
+            return Err(MemoryStorageError::CollectionNotFound(
+                collection.to_owned(),
+            ));
+        }
+
+        let hrefs = self
+            .items
+            .values()
+            .filter(|item| item.path.parent() == Some(collection))
+            .map(|item| ItemHref {
+                path: item.path.clone(),
+                format: item.kind.format(),
+                etag: item.etag.clone(),
+            })
+            .collect();
+
+        Ok(hrefs)
+    }
+
+    fn get(&mut self, path: &Path) -> Result<Item, Self::Error> {
+        Ok(self.item_mut(path, None)?.clone())
+    }
+
+    fn put(&mut self, item: &Item, etag: Option<&Etag>) -> Result<Etag, Self::Error> {
+        let collection = item.path.parent().unwrap_or(Path::new(""));
+
+        if !self.collections.contains_key(collection) {
+            return Err(MemoryStorageError::CollectionNotFound(
+                collection.to_owned(),
+            ));
+        }
+
+        if etag.is_some() {
+            self.item_mut(&item.path, etag)?;
+        }
+
+        let new_etag = Etag::from_contents(item.to_bytes());
+
+        let mut item = item.clone();
+        item.etag = Some(new_etag.clone());
+        self.items.insert(item.path.clone(), item);
+
+        Ok(new_etag)
+    }
+
+    fn delete(&mut self, path: &Path, etag: Option<&Etag>) -> Result<(), Self::Error> {
+        self.item_mut(path, etag)?;
+        self.items.remove(path);
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+//! Module dedicated to the storage abstraction.
+//!
+//! A storage exposes the operations needed to synchronize or migrate
+//! collections, regardless of where items are actually stored. Sync
+//! and migration logic can then be written once against the
+//! [`Storage`] trait, and tested against the in-memory backend.
+//!
+//! See [`vdir::VdirStorage`] and [`memory::MemoryStorage`].
+
+pub mod memory;
+pub mod vdir;
+
+use std::{collections::HashSet, path::Path};
+
+use crate::{
+    collection::Collection,
+    etag::Etag,
+    item::{Item, ItemHref},
+};
+
+/// The storage abstraction.
+///
+/// Collections and items are identified by their path, which does
+/// not need to match a filesystem path: it may be a virtual path or
+/// the path of a URL, depending on the backend.
+///
+/// Unlike coroutines, storage operations are blocking: backends are
+/// responsible for processing their own I/O.
+pub trait Storage {
+    /// The error returned by storage operations.
+    type Error: std::error::Error;
+
+    /// Lists the collections of the storage.
+    fn list_collections(&mut self) -> Result<HashSet<Collection>, Self::Error>;
+
+    /// Lists the item references of the given collection, with their
+    /// entity tag.
+    fn list_hrefs(&mut self, collection: &Path) -> Result<HashSet<ItemHref>, Self::Error>;
+
+    /// Gets the item at the given path, with its entity tag.
+    fn get(&mut self, path: &Path) -> Result<Item, Self::Error>;
+
+    /// Creates or replaces the given item, and returns its new
+    /// entity tag.
+    ///
+    /// When an entity tag is given, the item is replaced only if its
+    /// current entity tag matches.
+    fn put(&mut self, item: &Item, etag: Option<&Etag>) -> Result<Etag, Self::Error>;
+
+    /// Deletes the item at the given path.
+    ///
+    /// When an entity tag is given, the item is deleted only if its
+    /// current entity tag matches.
+    fn delete(&mut self, path: &Path, etag: Option<&Etag>) -> Result<(), Self::Error>;
+}
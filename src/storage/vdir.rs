@@ -0,0 +1,151 @@
+//! Module dedicated to the Vdir storage.
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use io_fs::io::FsIo;
+use thiserror::Error;
+
+use crate::{
+    collection::Collection,
+    coroutines::{
+        delete_item::{DeleteItem, DeleteItemError, DeleteItemResult},
+        list_collections::{ListCollections, ListCollectionsError, ListCollectionsResult},
+        list_item_hrefs::{ListItemHrefs, ListItemHrefsError, ListItemHrefsResult},
+        read_item::{ReadItem, ReadItemError, ReadItemResult},
+        update_item::{UpdateItem, UpdateItemError, UpdateItemResult},
+    },
+    etag::Etag,
+    item::{Item, ItemHref},
+    runtime::HandBack,
+    storage::Storage,
+};
+
+/// Errors that can occur during Vdir storage operations.
+#[derive(Debug, Error)]
+pub enum VdirStorageError {
+    /// The I/O handler failed to process an I/O.
+    #[error("Process Vdir storage I/O error")]
+    Io(#[source] io::Error),
+
+    /// An error occured during the collections listing.
+    #[error(transparent)]
+    ListCollections(#[from] ListCollectionsError),
+
+    /// An error occured during the item references listing.
+    #[error(transparent)]
+    ListHrefs(#[from] ListItemHrefsError),
+
+    /// An error occured during the item reading.
+    #[error(transparent)]
+    Get(#[from] ReadItemError),
+
+    /// An error occured during the item saving.
+    #[error(transparent)]
+    Put(#[from] UpdateItemError),
+
+    /// An error occured during the item deletion.
+    #[error(transparent)]
+    Delete(#[from] DeleteItemError),
+}
+
+/// The Vdir storage.
+///
+/// Drives the Vdir coroutines, using the given handler to process
+/// their I/O. The standard runtime of io-fs can be used as handler:
+/// failed read requests are handed back to the coroutines by the
+/// storage itself (see [`HandBack`]).
+pub struct VdirStorage<F: FnMut(FsIo) -> io::Result<FsIo>> {
+    root: PathBuf,
+    runtime: HandBack<F>,
+}
+
+impl<F: FnMut(FsIo) -> io::Result<FsIo>> VdirStorage<F> {
+    /// Creates a new Vdir storage from the given root directory path
+    /// and I/O handler.
+    pub fn new(root: impl Into<PathBuf>, handle: F) -> Self {
+        Self {
+            root: root.into(),
+            runtime: HandBack::new(handle),
+        }
+    }
+
+    fn handle(&mut self, io: FsIo) -> Result<Option<FsIo>, VdirStorageError> {
+        self.runtime
+            .handle(io)
+            .map(Some)
+            .map_err(VdirStorageError::Io)
+    }
+}
+
+impl<F: FnMut(FsIo) -> io::Result<FsIo>> Storage for VdirStorage<F> {
+    type Error = VdirStorageError;
+
+    fn list_collections(&mut self) -> Result<HashSet<Collection>, Self::Error> {
+        let mut arg = None;
+        let mut coroutine = ListCollections::new(&self.root);
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                ListCollectionsResult::Ok(collections) => break Ok(collections),
+                ListCollectionsResult::Err(err) => break Err(err.into()),
+                ListCollectionsResult::Io(io) => arg = self.handle(io)?,
+            }
+        }
+    }
+
+    fn list_hrefs(&mut self, collection: &Path) -> Result<HashSet<ItemHref>, Self::Error> {
+        let mut arg = None;
+        let mut coroutine = ListItemHrefs::new(collection).with_etags(true);
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                ListItemHrefsResult::Ok(hrefs) => break Ok(hrefs),
+                ListItemHrefsResult::Err(err) => break Err(err.into()),
+                ListItemHrefsResult::Io(io) => arg = self.handle(io)?,
+            }
+        }
+    }
+
+    fn get(&mut self, path: &Path) -> Result<Item, Self::Error> {
+        let mut arg = None;
+        let mut coroutine = ReadItem::new(path);
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                ReadItemResult::Ok(item) => break Ok(item),
+                ReadItemResult::Err(err) => break Err(err.into()),
+                ReadItemResult::Io(io) => arg = self.handle(io)?,
+            }
+        }
+    }
+
+    fn put(&mut self, item: &Item, etag: Option<&Etag>) -> Result<Etag, Self::Error> {
+        let mut arg = None;
+        let mut coroutine = UpdateItem::new(item.clone(), etag.cloned());
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                UpdateItemResult::Ok => break Ok(Etag::from_contents(item.to_bytes())),
+                UpdateItemResult::Err(err) => break Err(err.into()),
+                UpdateItemResult::Io(io) => arg = self.handle(io)?,
+            }
+        }
+    }
+
+    fn delete(&mut self, path: &Path, etag: Option<&Etag>) -> Result<(), Self::Error> {
+        let mut arg = None;
+        let mut coroutine = DeleteItem::new(path, etag.cloned());
+
+        loop {
+            match coroutine.resume(arg.take()) {
+                DeleteItemResult::Ok => break Ok(()),
+                DeleteItemResult::Err(err) => break Err(err.into()),
+                DeleteItemResult::Io(io) => arg = self.handle(io)?,
+            }
+        }
+    }
+}
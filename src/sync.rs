@@ -0,0 +1,284 @@
+//! Module dedicated to Vdir collections synchronization.
+//!
+//! See [`crate::coroutines::sync_collection::SyncCollection`].
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    etag::Etag,
+    item::Item,
+    jsonl::{self, JsonLinesError},
+};
+
+/// The current version of the synchronization status file format.
+pub const SYNC_STATUS_VERSION: u64 = 1;
+
+/// Errors that can occur while decoding a synchronization status.
+#[derive(Clone, Debug, Error)]
+pub enum SyncStatusError {
+    /// The status file does not start with a valid header.
+    #[error("Missing or invalid sync status header")]
+    InvalidHeader,
+
+    /// The status file has been written by an unsupported version
+    /// of the format.
+    #[error("Unsupported sync status version {0}")]
+    UnsupportedVersion(u64),
+
+    /// A status line could not be decoded.
+    #[error("Invalid sync status at line {0}: {1}")]
+    InvalidLine(usize, String),
+
+    /// The legacy vdirsyncer JSON status could not be decoded.
+    #[error("Invalid vdirsyncer JSON sync status: {0}")]
+    InvalidVdirsyncerStatus(String),
+}
+
+impl From<JsonLinesError> for SyncStatusError {
+    fn from(err: JsonLinesError) -> Self {
+        match err {
+            JsonLinesError::InvalidHeader => Self::InvalidHeader,
+            JsonLinesError::UnsupportedVersion(version) => Self::UnsupportedVersion(version),
+            JsonLinesError::InvalidLine(n, err) => Self::InvalidLine(n, err),
+        }
+    }
+}
+
+/// The synchronization side.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SyncSide {
+    /// The first collection (left side).
+    A,
+
+    /// The second collection (right side).
+    B,
+}
+
+/// The synchronization status.
+///
+/// Keeps track, for each item identifier, of the item's href and
+/// etag on both sides, as they were at the end of the last
+/// synchronization. The item identifier is the item's UID, or its
+/// etag when the item does not have a UID.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncStatus {
+    /// The status items, indexed by item identifier.
+    pub items: HashMap<String, SyncStatusItem>,
+}
+
+impl SyncStatus {
+    /// Decodes a synchronization status from the given raw
+    /// contents.
+    ///
+    /// The status file format is JSON lines (UTF-8). The first line
+    /// is a header containing the format version, each following
+    /// line describes the status of one item:
+    ///
+    /// ```text
+    /// {"version":1}
+    /// {"ident":"uid","a":{"href":"uid.vcf","etag":"…"},"b":{"href":"uid.vcf","etag":"…"}}
+    /// ```
+    ///
+    /// Empty lines are ignored.
+    pub fn from_bytes(contents: impl AsRef<[u8]>) -> Result<Self, SyncStatusError> {
+        let lines: Vec<StatusLine> = jsonl::decode(contents.as_ref(), SYNC_STATUS_VERSION)?;
+
+        let items = lines
+            .into_iter()
+            .map(|line| {
+                let item = SyncStatusItem {
+                    a: SyncStatusHref {
+                        href: line.a.href,
+                        etag: line.a.etag.into(),
+                    },
+                    b: SyncStatusHref {
+                        href: line.b.href,
+                        etag: line.b.etag.into(),
+                    },
+                };
+
+                (line.ident, item)
+            })
+            .collect();
+
+        Ok(Self { items })
+    }
+
+    /// Encodes the synchronization status into raw contents.
+    ///
+    /// Items are sorted by identifier, so that the same status
+    /// always gives the same contents.
+    ///
+    /// See [`SyncStatus::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut idents: Vec<_> = self.items.keys().collect();
+        idents.sort();
+
+        let lines = idents.into_iter().map(|ident| {
+            let item = &self.items[ident];
+
+            StatusLine {
+                ident: ident.clone(),
+                a: StatusHref {
+                    href: item.a.href.clone(),
+                    etag: item.a.etag.to_string(),
+                },
+                b: StatusHref {
+                    href: item.b.href.clone(),
+                    etag: item.b.etag.to_string(),
+                },
+            }
+        });
+
+        jsonl::encode(SYNC_STATUS_VERSION, lines)
+    }
+
+    /// Decodes a synchronization status from the given legacy
+    /// vdirsyncer JSON status contents.
+    ///
+    /// Only the JSON status files written by vdirsyncer before 0.16
+    /// are supported: the first layout maps identifiers to `[href_a,
+    /// etag_a, href_b, etag_b]`, the second one maps identifiers to
+    /// `[meta_a, meta_b]` objects containing `href` and `etag` keys.
+    /// vdirsyncer 0.16 and later keep their status in a SQLite
+    /// database, which cannot be imported.
+    ///
+    /// vdirsyncer etags are based on file modification times, they
+    /// cannot match the ones of this library: items that are
+    /// identical on both sides are recognized during the first
+    /// synchronization, other ones are reported as conflicts.
+    pub fn from_vdirsyncer_json(contents: impl AsRef<[u8]>) -> Result<Self, SyncStatusError> {
+        let invalid = |err: &str| SyncStatusError::InvalidVdirsyncerStatus(err.to_owned());
+
+        let status: HashMap<String, Value> = match serde_json::from_slice(contents.as_ref()) {
+            Ok(status) => status,
+            Err(err) => return Err(invalid(&err.to_string())),
+        };
+
+        let mut items = HashMap::new();
+
+        for (ident, value) in status {
+            let item = match value.as_array().map(Vec::as_slice) {
+                Some([href_a, etag_a, href_b, etag_b]) => SyncStatusItem {
+                    a: vdirsyncer_href(href_a, etag_a).ok_or_else(|| invalid(&ident))?,
+                    b: vdirsyncer_href(href_b, etag_b).ok_or_else(|| invalid(&ident))?,
+                },
+                Some([meta_a, meta_b]) => SyncStatusItem {
+                    a: vdirsyncer_href(&meta_a["href"], &meta_a["etag"])
+                        .ok_or_else(|| invalid(&ident))?,
+                    b: vdirsyncer_href(&meta_b["href"], &meta_b["etag"])
+                        .ok_or_else(|| invalid(&ident))?,
+                },
+                _ => return Err(invalid(&ident)),
+            };
+
+            items.insert(ident, item);
+        }
+
+        Ok(Self { items })
+    }
+}
+
+/// The synchronization status of an item.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyncStatusItem {
+    /// The last known state of the item on the side A.
+    pub a: SyncStatusHref,
+
+    /// The last known state of the item on the side B.
+    pub b: SyncStatusHref,
+}
+
+/// The last known state of an item on one side.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyncStatusHref {
+    /// The item's file name, relative to its collection.
+    pub href: String,
+
+    /// The item's entity tag.
+    pub etag: Etag,
+}
+
+/// A change applied during the synchronization.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SyncChange {
+    /// The item has been created on the given side, at the given
+    /// href.
+    Create(SyncSide, String),
+
+    /// The item has been updated on the given side, at the given
+    /// href.
+    Update(SyncSide, String),
+
+    /// The item has been deleted on the given side, at the given
+    /// href.
+    Delete(SyncSide, String),
+}
+
+/// A conflict detected during the synchronization.
+///
+/// Occurs when the same item has been modified on both sides since
+/// the last synchronization, or when an item has been modified in
+/// the meantime, right before being updated or deleted. Conflicting
+/// items are left untouched.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyncConflict {
+    /// The conflicting item's identifier.
+    pub ident: String,
+
+    /// The item on the side A, `None` when it does not exist or
+    /// cannot be parsed.
+    pub a: Option<Item>,
+
+    /// The item on the side B, `None` when it does not exist or
+    /// cannot be parsed.
+    pub b: Option<Item>,
+}
+
+/// The synchronization report.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncReport {
+    /// The new synchronization status, that should be persisted and
+    /// given to the next synchronization.
+    pub status: SyncStatus,
+
+    /// The changes applied on both sides.
+    pub changes: Vec<SyncChange>,
+
+    /// The conflicts that could not be solved.
+    pub conflicts: Vec<SyncConflict>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StatusLine {
+    ident: String,
+    a: StatusHref,
+    b: StatusHref,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StatusHref {
+    href: String,
+    etag: String,
+}
+
+fn vdirsyncer_href(href: &Value, etag: &Value) -> Option<SyncStatusHref> {
+    let href = href.as_str()?.to_owned();
+
+    // NOTE: vdirsyncer etags are either strings or floats
+    // (modification times)
+    let etag = match etag {
+        Value::String(etag) => etag.clone(),
+        Value::Number(etag) => etag.to_string(),
+        _ => return None,
+    };
+
+    Some(SyncStatusHref {
+        href,
+        etag: etag.into(),
+    })
+}
@@ -0,0 +1,48 @@
+//! Module dedicated to Vdir temporary files.
+//!
+//! Items and metadata files are never written in place: they are
+//! first written into a temporary file, which is then moved to its
+//! final path. Temporary files are hidden (dot-prefixed) and carry a
+//! random suffix, so that concurrent writers never share the same
+//! temporary file.
+
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::constants::TMP;
+
+/// Builds a unique temporary file path for the given file path.
+///
+/// The temporary file lives in the same directory as the given
+/// path, so that it can be atomically renamed to it. For example,
+/// `/path/to/item.vcf` gives something like
+/// `/path/to/.item.vcf.0b6bb9ad0e1f4bd8b1e0e8b9b3f6c6ac.tmp`.
+pub fn tmp_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let suffix = Uuid::new_v4().simple();
+
+    let mut name = String::from(".");
+    if let Some(file_name) = path.file_name() {
+        name.push_str(&file_name.to_string_lossy());
+    }
+    name.push_str(&format!(".{suffix}.{TMP}"));
+
+    path.with_file_name(name)
+}
+
+/// Returns `true` if the given path looks like a temporary file path
+/// built by [`tmp_path`].
+pub fn is_tmp_path(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+
+    name.as_encoded_bytes().starts_with(b".") && ext == TMP
+}
@@ -0,0 +1,65 @@
+//! Helpers shared by integration tests.
+
+#![allow(dead_code)]
+
+use std::{cell::RefCell, io};
+
+use calcard::{icalendar::ICalendar, vcard::VCard};
+use io_fs::{io::FsIo, runtimes};
+use io_vdir::{item::ItemKind, runtime::HandBack};
+
+type Handle = fn(FsIo) -> io::Result<FsIo>;
+
+thread_local! {
+    static RUNTIME: RefCell<HandBack<Handle>> = RefCell::new(HandBack::new(runtimes::std::handle));
+}
+
+/// Processes the given I/O request using the standard runtime,
+/// handing back failed read requests.
+pub fn handle(io: FsIo) -> FsIo {
+    RUNTIME
+        .with_borrow_mut(|runtime| runtime.handle(io))
+        .unwrap()
+}
+
+/// Builds a vCard 4.0 with the given UID and extra content lines.
+pub fn vcard(uid: &str, lines: &[&str]) -> String {
+    let mut contents = format!("BEGIN:VCARD\r\nVERSION:4.0\r\nUID:{uid}\r\n");
+
+    for line in lines {
+        contents.push_str(line);
+        contents.push_str("\r\n");
+    }
+
+    contents.push_str("END:VCARD\r\n");
+    contents
+}
+
+/// Builds an iCalendar event with the given UID and extra content
+/// lines, to be wrapped with [`ical`].
+pub fn vevent(uid: &str, lines: &[&str]) -> String {
+    let mut contents = format!("BEGIN:VEVENT\r\nUID:{uid}\r\n");
+
+    for line in lines {
+        contents.push_str(line);
+        contents.push_str("\r\n");
+    }
+
+    contents.push_str("END:VEVENT\r\n");
+    contents
+}
+
+/// Builds an iCalendar 2.0 wrapping the given raw components.
+pub fn ical(components: &str) -> String {
+    format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:test\r\n{components}END:VCALENDAR\r\n")
+}
+
+/// Same as [`vcard`], parsed into an item kind.
+pub fn vcard_kind(uid: &str, lines: &[&str]) -> ItemKind {
+    ItemKind::Vcard(VCard::parse(vcard(uid, lines)).unwrap())
+}
+
+/// Same as [`ical`], parsed into an item kind.
+pub fn ical_kind(components: &str) -> ItemKind {
+    ItemKind::Ical(ICalendar::parse(ical(components)).unwrap())
+}
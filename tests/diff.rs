@@ -0,0 +1,85 @@
+use io_vdir::diff::{diff, ComponentChange, DiffError, ItemDiff, PropertyDiff};
+
+use crate::common::{ical_kind, vcard_kind, vevent};
+
+mod common;
+
+#[test]
+fn diff_items() {
+    // should report no change between identical items
+
+    let old = vcard_kind("abc", &["FN:Doe", "TEL:123"]);
+
+    assert!(diff(&old, &old).unwrap().is_empty());
+
+    // should report added, removed and changed vCard properties
+
+    let new = vcard_kind("abc", &["FN:John Doe", "EMAIL;TYPE=work:a@localhost"]);
+
+    let ItemDiff::Vcard(item_diff) = diff(&old, &new).unwrap() else {
+        panic!("should be a vCard diff");
+    };
+
+    assert_eq!(item_diff.properties.len(), 3);
+    assert!(matches!(
+        &item_diff.properties[0],
+        PropertyDiff::Changed(old, new) if old.values != new.values
+    ));
+    assert!(matches!(&item_diff.properties[1], PropertyDiff::Removed(_)));
+    assert!(
+        matches!(&item_diff.properties[2], PropertyDiff::Added(entry) if !entry.params.is_empty())
+    );
+
+    // should render the diff line by line
+
+    let output = ItemDiff::Vcard(item_diff).to_string();
+
+    assert_eq!(
+        output,
+        "- FN:Doe\n+ FN:John Doe\n- TEL:123\n+ EMAIL;TYPE=WORK:a@localhost\n"
+    );
+
+    // should report property changes per iCalendar component
+
+    let old = ical_kind(
+        &[
+            vevent("a", &["SUMMARY:Meeting"]),
+            vevent("b", &["SUMMARY:Lunch"]),
+        ]
+        .concat(),
+    );
+    let new = ical_kind(
+        &[
+            vevent("a", &["SUMMARY:Standup"]),
+            vevent("c", &["SUMMARY:Dinner"]),
+        ]
+        .concat(),
+    );
+
+    let ItemDiff::Ical(item_diff) = diff(&old, &new).unwrap() else {
+        panic!("should be an iCalendar diff");
+    };
+
+    let changes: Vec<_> = item_diff
+        .components
+        .iter()
+        .map(|component| (component.change, component.uid.as_deref()))
+        .collect();
+
+    assert_eq!(
+        changes,
+        vec![
+            (ComponentChange::Changed, Some("a")),
+            (ComponentChange::Removed, Some("b")),
+            (ComponentChange::Added, Some("c")),
+        ]
+    );
+    assert_eq!(item_diff.components[0].properties.len(), 1);
+
+    // should not diff items of different formats
+
+    assert!(matches!(
+        diff(&old, &vcard_kind("abc", &[])),
+        Err(DiffError::FormatMismatch)
+    ));
+}
@@ -0,0 +1,123 @@
+use io_vdir::merge::{merge, MergeConflict, MergeError};
+
+use crate::common::{ical_kind, vcard_kind, vevent};
+
+mod common;
+
+#[test]
+fn merge_items() {
+    // should merge disjoint vCard edits
+
+    let base = vcard_kind("abc", &["FN:Doe", "TEL:123", "EMAIL:a@localhost"]);
+    let local = vcard_kind("abc", &["FN:Doe", "TEL:456", "EMAIL:a@localhost"]);
+    let remote = vcard_kind(
+        "abc",
+        &["FN:Doe", "TEL:123", "EMAIL:b@localhost", "NOTE:new"],
+    );
+
+    let merged = merge(&base, &local, &remote).unwrap();
+    let expected = vcard_kind(
+        "abc",
+        &["FN:Doe", "TEL:456", "EMAIL:b@localhost", "NOTE:new"],
+    );
+
+    assert_eq!(merged, expected);
+
+    // should merge removals
+
+    let remote = vcard_kind("abc", &["FN:Doe", "TEL:123"]);
+    let merged = merge(&base, &local, &remote).unwrap();
+
+    assert_eq!(merged, vcard_kind("abc", &["FN:Doe", "TEL:456"]));
+
+    // should keep the most recent revision
+
+    let base = vcard_kind("abc", &["FN:Doe", "REV:20240101T000000Z"]);
+    let local = vcard_kind("abc", &["FN:John", "REV:20240103T000000Z"]);
+    let remote = vcard_kind("abc", &["FN:Doe", "NOTE:new", "REV:20240102T000000Z"]);
+
+    let merged = merge(&base, &local, &remote).unwrap();
+    let expected = vcard_kind("abc", &["FN:John", "REV:20240103T000000Z", "NOTE:new"]);
+
+    assert_eq!(merged, expected);
+
+    // should report conflicting vCard edits
+
+    let base = vcard_kind("abc", &["FN:Doe", "TEL:123"]);
+    let local = vcard_kind("abc", &["FN:Doe", "TEL:456"]);
+    let remote = vcard_kind("abc", &["FN:Jane", "TEL:789"]);
+
+    let Err(MergeError::Conflicts(conflicts)) = merge(&base, &local, &remote) else {
+        panic!("should report conflicts");
+    };
+
+    assert_eq!(conflicts.len(), 1);
+    assert!(matches!(
+        &conflicts[0],
+        MergeConflict::Vcard { local, remote, .. } if local.len() == 1 && remote.len() == 1
+    ));
+
+    // should merge disjoint iCalendar edits
+
+    let base = ical_kind(&vevent(
+        "abc",
+        &["SUMMARY:Meeting", "LOCATION:Office", "SEQUENCE:0"],
+    ));
+    let local = ical_kind(&vevent(
+        "abc",
+        &["SUMMARY:Standup", "LOCATION:Office", "SEQUENCE:1"],
+    ));
+    let remote = ical_kind(&vevent(
+        "abc",
+        &["SUMMARY:Meeting", "LOCATION:Home", "SEQUENCE:2"],
+    ));
+
+    let merged = merge(&base, &local, &remote).unwrap();
+    let expected = ical_kind(&vevent(
+        "abc",
+        &["SUMMARY:Standup", "LOCATION:Home", "SEQUENCE:2"],
+    ));
+
+    assert_eq!(merged, expected);
+
+    // should keep the most recent timestamp, whatever its parameters
+
+    let base = ical_kind(&vevent("abc", &["DTSTAMP:20240101T000000Z"]));
+    let local = ical_kind(&vevent("abc", &["DTSTAMP;X-TEST=a:20240102T000000Z"]));
+    let remote = ical_kind(&vevent("abc", &["DTSTAMP:20240103T000000Z"]));
+
+    let merged = merge(&base, &local, &remote).unwrap();
+
+    assert_eq!(merged, remote);
+
+    // should report conflicting iCalendar edits
+
+    let base = ical_kind(&vevent(
+        "abc",
+        &["SUMMARY:Meeting", "LOCATION:Office", "SEQUENCE:0"],
+    ));
+
+    let remote = ical_kind(&vevent(
+        "abc",
+        &["SUMMARY:Lunch", "LOCATION:Office", "SEQUENCE:2"],
+    ));
+
+    let Err(MergeError::Conflicts(conflicts)) = merge(&base, &local, &remote) else {
+        panic!("should report conflicts");
+    };
+
+    assert_eq!(conflicts.len(), 1);
+    assert!(matches!(
+        &conflicts[0],
+        MergeConflict::Ical { component: 1, .. }
+    ));
+
+    // should not merge items of different formats
+
+    let remote = vcard_kind("abc", &["FN:Doe"]);
+
+    assert!(matches!(
+        merge(&base, &local, &remote),
+        Err(MergeError::FormatMismatch)
+    ));
+}
@@ -0,0 +1,168 @@
+use std::{fs, path::Path};
+
+use io_vdir::{
+    constants::INDEX,
+    coroutines::{
+        build_index::{BuildIndex, BuildIndexResult},
+        query_index::{QueryIndex, QueryIndexResult},
+        read_index::{ReadIndex, ReadIndexError, ReadIndexResult},
+        refresh_index::{RefreshIndex, RefreshIndexResult},
+    },
+    index::{Index, IndexError},
+    item::ItemFormat,
+};
+use tempfile::tempdir;
+
+use crate::common::{handle, vcard};
+
+mod common;
+
+fn build(collection: &Path) -> (Index, usize) {
+    let mut arg = None;
+    let mut build = BuildIndex::new(collection);
+
+    loop {
+        match build.resume(arg) {
+            BuildIndexResult::Ok(index, errors) => break (index, errors.len()),
+            BuildIndexResult::Io(io) => arg = Some(handle(io)),
+            BuildIndexResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn refresh(collection: &Path) -> Index {
+    let mut arg = None;
+    let mut refresh = RefreshIndex::new(collection);
+
+    loop {
+        match refresh.resume(arg) {
+            RefreshIndexResult::Ok(index, _) => break index,
+            RefreshIndexResult::Io(io) => arg = Some(handle(io)),
+            RefreshIndexResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn read(collection: &Path) -> Result<Index, ReadIndexError> {
+    let mut arg = None;
+    let mut read = ReadIndex::new(collection);
+
+    loop {
+        match read.resume(arg) {
+            ReadIndexResult::Ok(index) => break Ok(index),
+            ReadIndexResult::Io(io) => arg = Some(handle(io)),
+            ReadIndexResult::Err(err) => break Err(err),
+        }
+    }
+}
+
+fn query(collection: &Path, query: &str) -> Vec<String> {
+    let mut arg = None;
+    let mut query = QueryIndex::new(collection, query);
+
+    loop {
+        match query.resume(arg) {
+            QueryIndexResult::Ok(entries) => {
+                break entries.into_iter().map(|(href, _)| href).collect()
+            }
+            QueryIndexResult::Io(io) => arg = Some(handle(io)),
+            QueryIndexResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+#[test]
+fn std_index() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let collection = workdir.path();
+
+    fs::write(
+        collection.join("doe.vcf"),
+        vcard("doe", &["FN:John Doe", "EMAIL:john@acme.example"]),
+    )
+    .unwrap();
+    fs::write(
+        collection.join("roe.vcf"),
+        vcard("roe", &["FN:Jane Roe", "EMAIL:jane@home.example"]),
+    )
+    .unwrap();
+    fs::write(
+        collection.join("dentist.ics"),
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:test\r\nBEGIN:VEVENT\r\nUID:dentist\r\nDTSTART:20250101T100000Z\r\nSUMMARY:Dentist\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .unwrap();
+    fs::write(collection.join("invalid.vcf"), "not a vcard").unwrap();
+
+    // should give no entry when the collection has no index
+
+    assert!(query(collection, "").is_empty());
+
+    // should build the index of valid items
+
+    let (index, errors) = build(collection);
+
+    assert_eq!(errors, 1);
+    assert_eq!(index.entries.len(), 3);
+    assert!(collection.join(INDEX).exists());
+    assert_eq!(read(collection).unwrap(), index);
+
+    let doe = &index.entries["doe.vcf"];
+
+    assert_eq!(doe.format, ItemFormat::Vcard);
+    assert_eq!(doe.uid.as_deref(), Some("doe"));
+    assert_eq!(doe.name.as_deref(), Some("John Doe"));
+    assert_eq!(doe.emails, vec!["john@acme.example"]);
+
+    let dentist = &index.entries["dentist.ics"];
+
+    assert_eq!(dentist.format, ItemFormat::Ical);
+    assert_eq!(dentist.name.as_deref(), Some("Dentist"));
+    assert_eq!(dentist.start, Some(1735725600));
+
+    // should query the index by UID, name or email
+
+    assert_eq!(
+        query(collection, ""),
+        vec!["dentist.ics", "roe.vcf", "doe.vcf"]
+    );
+    assert_eq!(query(collection, "ACME"), vec!["doe.vcf"]);
+    assert_eq!(query(collection, "roe"), vec!["roe.vcf"]);
+    assert!(query(collection, "nobody").is_empty());
+
+    // should only parse again the items whose etag changed
+
+    let mut index = index;
+    index.entries.get_mut("roe.vcf").unwrap().name = Some("Cached".into());
+    fs::write(collection.join(INDEX), index.to_bytes()).unwrap();
+
+    fs::write(
+        collection.join("doe.vcf"),
+        vcard("doe", &["FN:Johnny Doe", "EMAIL:john@acme.example"]),
+    )
+    .unwrap();
+    fs::remove_file(collection.join("dentist.ics")).unwrap();
+
+    let index = refresh(collection);
+
+    assert_eq!(index.entries.len(), 2);
+    assert_eq!(index.entries["doe.vcf"].name.as_deref(), Some("Johnny Doe"));
+    assert_eq!(index.entries["roe.vcf"].name.as_deref(), Some("Cached"));
+
+    // should rebuild indexes that cannot be decoded
+
+    fs::write(collection.join(INDEX), "{\"version\":0}\n").unwrap();
+
+    assert!(matches!(
+        read(collection),
+        Err(ReadIndexError::DecodeError(
+            IndexError::UnsupportedVersion(0),
+            _
+        ))
+    ));
+
+    let index = refresh(collection);
+
+    assert_eq!(index.entries["roe.vcf"].name.as_deref(), Some("Jane Roe"));
+}
@@ -0,0 +1,138 @@
+use std::{fs, path::Path};
+
+use io_vdir::{
+    coroutines::lookup_emails::{LookupEmails, LookupEmailsResult},
+    lookup::{format_mutt, EmailContact},
+};
+use tempfile::tempdir;
+
+use crate::common::handle;
+
+mod common;
+
+fn lookup(collections: &[&Path], query: &str) -> Vec<EmailContact> {
+    let mut arg = None;
+    let mut lookup = LookupEmails::new(collections, query);
+
+    loop {
+        match lookup.resume(arg) {
+            LookupEmailsResult::Ok(contacts, _) => break contacts,
+            LookupEmailsResult::Io(io) => arg = Some(handle(io)),
+            LookupEmailsResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn emails(contacts: &[EmailContact]) -> Vec<&str> {
+    contacts.iter().map(|c| c.email.as_str()).collect()
+}
+
+#[test]
+fn std_lookup() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let personal = workdir.path().join("personal");
+    let work = workdir.path().join("work");
+
+    fs::create_dir(&personal).unwrap();
+    fs::create_dir(&work).unwrap();
+
+    fs::write(
+        personal.join("doe.vcf"),
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:doe\r\nFN:John Doe\r\nNICKNAME:Johnny,JD\r\nEMAIL;TYPE=home:john@home.example\r\nEMAIL;TYPE=work:jdoe@acme.example\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    fs::write(
+        work.join("roe.vcf"),
+        "BEGIN:VCARD\r\nVERSION:3.0\r\nUID:roe\r\nN:Roe;Jane;;Dr.;\r\nFN:\r\nEMAIL:jane@acme.example\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    // the same contact synchronized in both collections
+    fs::copy(personal.join("doe.vcf"), work.join("doe.vcf")).unwrap();
+
+    fs::write(
+        work.join("event.ics"),
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:test\r\nBEGIN:VEVENT\r\nUID:event\r\nSUMMARY:john@home.example\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .unwrap();
+
+    let collections = [personal.as_path(), work.as_path()];
+
+    // should return all the addresses of contacts whose name or
+    // nickname matches, without duplicates
+
+    let contacts = lookup(&collections, "john");
+
+    assert_eq!(
+        contacts,
+        vec![
+            EmailContact {
+                email: "jdoe@acme.example".into(),
+                name: "John Doe".into(),
+                extra: "work".into(),
+            },
+            EmailContact {
+                email: "john@home.example".into(),
+                name: "John Doe".into(),
+                extra: "home".into(),
+            },
+        ]
+    );
+
+    assert_eq!(emails(&lookup(&collections, "JD")).len(), 2);
+
+    // should return only the matching addresses otherwise
+
+    assert_eq!(
+        emails(&lookup(&collections, "acme")),
+        vec!["jane@acme.example", "jdoe@acme.example"]
+    );
+
+    // should fall back to the structured name
+
+    let contacts = lookup(&collections, "jane roe");
+
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0].name, "Dr. Jane Roe");
+    assert_eq!(contacts[0].extra, "");
+
+    // should only search the given collections
+
+    assert!(lookup(&[personal.as_path()], "jane").is_empty());
+    assert_eq!(lookup(&[personal.as_path()], "").len(), 2);
+
+    // should format the mutt query output
+
+    assert_eq!(
+        format_mutt(&lookup(&collections, "roe")),
+        "1 matching email address\njane@acme.example\tDr. Jane Roe\t\n"
+    );
+    assert_eq!(format_mutt(&[]), "0 matching email addresses\n");
+}
+
+#[test]
+fn std_lookup_escaped_names() {
+    let workdir = tempdir().unwrap();
+    let collection = workdir.path();
+
+    fs::write(
+        collection.join("smith.vcf"),
+        "BEGIN:VCARD\r\nVERSION:3.0\r\nUID:smith\r\nN:Smith\\, Jr.;Will\\;iam;;;\r\nNICKNAME:Bill\\, the Kid,Billy\r\nEMAIL:will@example.org\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    // should not split structured names on escaped separators
+
+    let contacts = lookup(&[collection], "");
+
+    assert_eq!(emails(&contacts), ["will@example.org"]);
+    assert_eq!(contacts[0].name, "Will;iam Smith, Jr.");
+
+    // should not split nicknames on escaped commas
+
+    assert_eq!(lookup(&[collection], "bill, the").len(), 1);
+    assert_eq!(lookup(&[collection], "billy").len(), 1);
+}
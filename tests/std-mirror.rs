@@ -0,0 +1,125 @@
+use std::{fs, path::Path};
+
+use io_fs::io::FsIo;
+use io_vdir::coroutines::mirror_collection::{
+    MirrorChange, MirrorCollection, MirrorCollectionResult,
+};
+use tempfile::tempdir;
+
+use crate::common::{handle, vcard};
+
+mod common;
+
+fn mirror(source: &Path, target: &Path, dry_run: bool) -> Vec<MirrorChange> {
+    let mut arg = None;
+    let mut mirror = MirrorCollection::new(source, target).with_dry_run(dry_run);
+
+    loop {
+        match mirror.resume(arg) {
+            MirrorCollectionResult::Ok(changes) => break changes,
+            MirrorCollectionResult::Io(io) => arg = Some(handle(io)),
+            MirrorCollectionResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+#[test]
+fn std_mirror() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let source = workdir.path().join("source");
+    let target = workdir.path().join("target");
+
+    fs::create_dir(&source).unwrap();
+    fs::create_dir(&target).unwrap();
+
+    fs::write(source.join("x.vcf"), vcard("x", &["FN:X"])).unwrap();
+    fs::write(source.join("y.vcf"), vcard("y", &["FN:Y"])).unwrap();
+    fs::write(target.join("y.vcf"), vcard("y", &["FN:Y0"])).unwrap();
+    fs::write(target.join("z.vcf"), vcard("z", &["FN:Z"])).unwrap();
+
+    // should only report planned changes in dry-run mode
+
+    let mut changes = mirror(&source, &target, true);
+    changes.sort_by_key(|change| format!("{change:?}"));
+
+    assert_eq!(
+        changes,
+        vec![
+            MirrorChange::Create("x.vcf".into()),
+            MirrorChange::Delete("z.vcf".into()),
+            MirrorChange::Update("y.vcf".into()),
+        ]
+    );
+    assert!(!target.join("x.vcf").exists());
+    assert!(target.join("z.vcf").exists());
+
+    // should mirror the source collection
+
+    let changes = mirror(&source, &target, false);
+
+    assert_eq!(changes.len(), 3);
+    assert!(target.join("x.vcf").exists());
+    assert!(!target.join("z.vcf").exists());
+    assert!(fs::read_to_string(target.join("y.vcf"))
+        .unwrap()
+        .contains("FN:Y\r\n"));
+
+    // should not change anything when already mirrored
+
+    assert!(mirror(&source, &target, false).is_empty());
+
+    // should not delete the counterpart of invalid source items
+
+    fs::write(source.join("x.vcf"), "not a vcard").unwrap();
+
+    assert!(mirror(&source, &target, false).is_empty());
+    assert!(target.join("x.vcf").exists());
+}
+
+#[test]
+fn std_mirror_many() {
+    let workdir = tempdir().unwrap();
+    let source = workdir.path().join("source");
+    let target = workdir.path().join("target");
+
+    fs::create_dir(&source).unwrap();
+    fs::create_dir(&target).unwrap();
+
+    for i in 0..100 {
+        fs::write(
+            source.join(format!("{i}.vcf")),
+            vcard(&i.to_string(), &["FN:X"]),
+        )
+        .unwrap();
+    }
+
+    // should list each collection once, and write new items at once
+
+    let mut arg = None;
+    let mut mirror = MirrorCollection::new(&source, &target);
+    let mut read_dirs = 0;
+    let mut create_files = 0;
+
+    let changes = loop {
+        match mirror.resume(arg) {
+            MirrorCollectionResult::Ok(changes) => break changes,
+            MirrorCollectionResult::Io(io) => {
+                match &io {
+                    FsIo::ReadDir(_) => read_dirs += 1,
+                    FsIo::CreateFile(_) | FsIo::CreateFiles(_) => create_files += 1,
+                    _ => (),
+                }
+
+                arg = Some(handle(io));
+            }
+            MirrorCollectionResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert_eq!(changes.len(), 100);
+    assert_eq!(read_dirs, 2);
+    assert_eq!(create_files, 1);
+    assert_eq!(fs::read_dir(&target).unwrap().count(), 100);
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use calcard::{
+    common::timezone::Tz,
+    icalendar::{ICalendarProperty, ICalendarValue},
+};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use io_vdir::coroutines::list_occurrences::{ListOccurrences, ListOccurrencesResult, Occurrence};
+use tempfile::tempdir;
+
+use crate::common::{handle, ical};
+
+mod common;
+
+fn list(coroutine: ListOccurrences) -> Vec<Occurrence> {
+    let (occurrences, truncated) = list_truncated(coroutine);
+    assert!(truncated.is_empty());
+    occurrences
+}
+
+fn list_truncated(mut coroutine: ListOccurrences) -> (Vec<Occurrence>, HashSet<PathBuf>) {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg) {
+            ListOccurrencesResult::Ok(occurrences, truncated, _) => break (occurrences, truncated),
+            ListOccurrencesResult::Io(io) => arg = Some(handle(io)),
+            ListOccurrencesResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn utc(day: u32, hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 1, day, hour, 0, 0).unwrap()
+}
+
+fn summary(occurrence: &Occurrence) -> String {
+    match occurrence
+        .component
+        .property(&ICalendarProperty::Summary)
+        .and_then(|entry| entry.values.first())
+    {
+        Some(ICalendarValue::Text(summary)) => summary.clone(),
+        _ => String::new(),
+    }
+}
+
+fn summarize(occurrences: &[Occurrence]) -> Vec<(String, DateTime<Utc>)> {
+    occurrences
+        .iter()
+        .map(|o| (summary(o), o.start.with_timezone(&Utc)))
+        .collect()
+}
+
+#[test]
+fn std_occurrences() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let collection: &Path = workdir.path();
+
+    fs::write(
+        collection.join("weekly.ics"),
+        ical(concat!(
+            "BEGIN:VEVENT\r\n",
+            "UID:weekly\r\n",
+            "DTSTART;TZID=Europe/Paris:20250106T090000\r\n",
+            "DTEND;TZID=Europe/Paris:20250106T100000\r\n",
+            "RRULE:FREQ=WEEKLY;COUNT=10\r\n",
+            "RDATE;TZID=Europe/Paris:20250108T090000\r\n",
+            "EXDATE;TZID=Europe/Paris:20250113T090000\r\n",
+            "SUMMARY:Weekly\r\n",
+            "END:VEVENT\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:weekly\r\n",
+            "RECURRENCE-ID;TZID=Europe/Paris:20250120T090000\r\n",
+            "DTSTART;TZID=Europe/Paris:20250120T140000\r\n",
+            "DTEND;TZID=Europe/Paris:20250120T150000\r\n",
+            "SUMMARY:Moved\r\n",
+            "END:VEVENT\r\n",
+        )),
+    )
+    .unwrap();
+
+    fs::write(
+        collection.join("single.ics"),
+        ical(concat!(
+            "BEGIN:VEVENT\r\n",
+            "UID:single\r\n",
+            "DTSTART:20250107T120000Z\r\n",
+            "DURATION:PT30M\r\n",
+            "SUMMARY:Single\r\n",
+            "END:VEVENT\r\n",
+        )),
+    )
+    .unwrap();
+
+    fs::write(
+        collection.join("floating.ics"),
+        ical(concat!(
+            "BEGIN:VTODO\r\n",
+            "UID:floating\r\n",
+            "DUE:20250109T230000\r\n",
+            "SUMMARY:Floating\r\n",
+            "END:VTODO\r\n",
+        )),
+    )
+    .unwrap();
+
+    fs::write(
+        collection.join("contact.vcf"),
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:contact\r\nFN:Doe\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    // should expand recurrences, skip excluded dates and apply
+    // overrides
+
+    let occurrences = list(ListOccurrences::new(collection, utc(6, 0), utc(21, 0)));
+
+    assert_eq!(
+        summarize(&occurrences),
+        vec![
+            ("Weekly".into(), utc(6, 8)),
+            ("Single".into(), utc(7, 12)),
+            ("Weekly".into(), utc(8, 8)),
+            ("Floating".into(), utc(9, 23)),
+            ("Moved".into(), utc(20, 13)),
+        ]
+    );
+
+    assert_eq!(occurrences[0].end.with_timezone(&Utc), utc(6, 9));
+    assert_eq!(
+        occurrences[1].end.with_timezone(&Utc),
+        utc(7, 12) + chrono::TimeDelta::minutes(30)
+    );
+
+    // should keep occurrences overlapping the range bounds only
+
+    let occurrences = list(ListOccurrences::new(collection, utc(6, 9), utc(7, 12)));
+
+    assert!(occurrences.is_empty());
+
+    let occurrences = list(ListOccurrences::new(collection, utc(27, 8), utc(27, 9)));
+
+    assert_eq!(summarize(&occurrences), vec![("Weekly".into(), utc(27, 8))]);
+
+    // should resolve floating dates with the default time zone
+
+    let tz = Tz::Fixed(FixedOffset::east_opt(2 * 3600).unwrap());
+    let occurrences =
+        list(ListOccurrences::new(collection, utc(9, 0), utc(10, 0)).with_default_tz(tz));
+
+    assert_eq!(
+        summarize(&occurrences),
+        vec![("Floating".into(), utc(9, 21))]
+    );
+
+    // should report items truncated by the expansion limit
+
+    let coroutine =
+        ListOccurrences::new(collection, utc(27, 0), utc(28, 0)).with_expansion_limit(3);
+    let (occurrences, truncated) = list_truncated(coroutine);
+
+    assert!(occurrences.is_empty());
+    assert!(truncated.contains(&collection.join("weekly.ics")));
+}
+
+#[test]
+fn std_occurrences_long_running() {
+    let workdir = tempdir().unwrap();
+    let collection: &Path = workdir.path();
+
+    fs::write(
+        collection.join("hourly.ics"),
+        ical(concat!(
+            "BEGIN:VEVENT\r\n",
+            "UID:hourly\r\n",
+            "SUMMARY:Hourly\r\n",
+            "DTSTART:20000101T000000Z\r\n",
+            "DURATION:PT30M\r\n",
+            "RRULE:FREQ=HOURLY\r\n",
+            "END:VEVENT\r\n",
+        )),
+    )
+    .unwrap();
+
+    fs::write(
+        collection.join("daily.ics"),
+        ical(concat!(
+            "BEGIN:VEVENT\r\n",
+            "UID:daily\r\n",
+            "SUMMARY:Daily\r\n",
+            "DTSTART:19700101T120000Z\r\n",
+            "DURATION:PT1H\r\n",
+            "RRULE:FREQ=DAILY\r\n",
+            "END:VEVENT\r\n",
+        )),
+    )
+    .unwrap();
+
+    // should expand long-running series up to the range end
+
+    let start = Utc.with_ymd_and_hms(2026, 10, 17, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap();
+    let occurrences = list(ListOccurrences::new(collection, start, end));
+    let summaries = summarize(&occurrences);
+
+    assert_eq!(summaries.len(), 25);
+    assert_eq!(summaries.first().unwrap(), &("Hourly".into(), start));
+    assert!(summaries.contains(&("Daily".into(), start + chrono::Duration::hours(12))));
+}
@@ -0,0 +1,124 @@
+use std::{fs, path::Path};
+
+use io_vdir::{
+    coroutines::query_items::{QueryItems, QueryItemsResult},
+    query::{Filter, FilterParseError, MatchType, PropertyFilter, TextMatch},
+};
+use tempfile::tempdir;
+
+use crate::common::handle;
+
+mod common;
+
+fn query(collection: &Path, filter: &str) -> Vec<String> {
+    let filter: Filter = filter.parse().unwrap();
+    let mut arg = None;
+    let mut query = QueryItems::new(collection, filter);
+
+    let items = loop {
+        match query.resume(arg) {
+            QueryItemsResult::Ok(items, _) => break items,
+            QueryItemsResult::Io(io) => arg = Some(handle(io)),
+            QueryItemsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    let mut stems: Vec<_> = items
+        .into_iter()
+        .map(|item| item.path.file_stem().unwrap().to_string_lossy().to_string())
+        .collect();
+
+    stems.sort();
+    stems
+}
+
+#[test]
+fn std_query() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let collection = workdir.path();
+
+    fs::write(
+        collection.join("doe.vcf"),
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:doe\r\nFN:John Doe\r\nEMAIL;TYPE=work:john@corp.example\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    fs::write(
+        collection.join("roe.vcf"),
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:roe\r\nFN:Jane Roe\r\nEMAIL;TYPE=home:jane@home.example\r\nTEL;TYPE=cell:+33 6 00 00 00 00\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    fs::write(
+        collection.join("meeting.ics"),
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:test\r\nBEGIN:VEVENT\r\nUID:meeting\r\nDTSTART:20250101T100000Z\r\nSUMMARY:Team Meeting\\, weekly\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .unwrap();
+
+    fs::write(collection.join("invalid.vcf"), "not a vcard").unwrap();
+
+    // should match text case-insensitively by default
+
+    assert_eq!(query(collection, "FN ~ doe"), vec!["doe"]);
+    assert_eq!(query(collection, "fn = 'jane roe'"), vec!["roe"]);
+    assert!(query(collection, "FN ~~ doe").is_empty());
+    assert_eq!(query(collection, "FN ^^ John"), vec!["doe"]);
+    assert_eq!(query(collection, "EMAIL $ .example"), vec!["doe", "roe"]);
+
+    // should match property existence and parameters
+
+    assert_eq!(query(collection, "TEL"), vec!["roe"]);
+    assert_eq!(query(collection, "EMAIL;TYPE=work"), vec!["doe"]);
+    assert_eq!(query(collection, "EMAIL;TYPE"), vec!["doe", "roe"]);
+    assert_eq!(query(collection, "TEL;TYPE=cell ^ \"+33\""), vec!["roe"]);
+
+    // should combine filters
+
+    assert_eq!(query(collection, "not EMAIL"), vec!["meeting"]);
+    assert_eq!(
+        query(collection, "EMAIL and not EMAIL;TYPE=work"),
+        vec!["roe"]
+    );
+    assert_eq!(
+        query(collection, "FN ~ doe or (TEL and FN ~ jane)"),
+        vec!["doe", "roe"]
+    );
+
+    // should match iCalendar components with unescaped values
+
+    assert_eq!(
+        query(collection, "VEVENT.SUMMARY = 'team meeting, weekly'"),
+        vec!["meeting"]
+    );
+    assert!(query(collection, "VTODO.SUMMARY").is_empty());
+    assert!(query(collection, "VEVENT.FN").is_empty());
+
+    // should build filters programmatically
+
+    let filter = Filter::from(
+        PropertyFilter::new("FN").with_text_match(TextMatch::new(MatchType::EndsWith, "ROE")),
+    );
+
+    assert_eq!(filter, "FN $ ROE".parse().unwrap());
+
+    // should reject invalid expressions
+
+    assert_eq!(
+        "FN ~".parse::<Filter>(),
+        Err(FilterParseError::UnexpectedEnd)
+    );
+    assert_eq!(
+        "FN ~ 'doe".parse::<Filter>(),
+        Err(FilterParseError::UnterminatedString)
+    );
+    assert_eq!(
+        "(FN and".parse::<Filter>(),
+        Err(FilterParseError::UnexpectedEnd)
+    );
+    assert_eq!(
+        "FN )".parse::<Filter>(),
+        Err(FilterParseError::UnexpectedToken(")".into()))
+    );
+}
@@ -0,0 +1,121 @@
+use std::{fs, path::Path};
+
+use io_vdir::{
+    coroutines::search_items::{SearchItems, SearchItemsResult},
+    search::SearchHit,
+};
+use tempfile::tempdir;
+
+use crate::common::handle;
+
+mod common;
+
+fn search(root: &Path, query: &str) -> Vec<SearchHit> {
+    let mut arg = None;
+    let mut search = SearchItems::new(root, query);
+
+    loop {
+        match search.resume(arg) {
+            SearchItemsResult::Ok(hits, _) => break hits,
+            SearchItemsResult::Io(io) => arg = Some(handle(io)),
+            SearchItemsResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn stems(hits: &[SearchHit]) -> Vec<String> {
+    hits.iter()
+        .map(|hit| {
+            hit.item
+                .path
+                .file_stem()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn std_search() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let root = workdir.path();
+    let contacts = root.join("contacts");
+    let calendar = root.join("calendar");
+
+    fs::create_dir(&contacts).unwrap();
+    fs::create_dir(&calendar).unwrap();
+
+    fs::write(
+        contacts.join("doe.vcf"),
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:doe\r\nFN:John Doe\r\nORG:ACME Inc.\r\nEMAIL:john@acme.example\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    fs::write(
+        contacts.join("roe.vcf"),
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:roe\r\nFN:Jane Roe\r\nEMAIL:jane@acmecorp.example\r\nNOTE:Dentist\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+
+    fs::write(
+        calendar.join("dentist.ics"),
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:test\r\nBEGIN:VEVENT\r\nUID:dentist\r\nDTSTART:20250101T100000Z\r\nSUMMARY:Dentist appointment\r\nLOCATION:Main street\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .unwrap();
+
+    // should search every collection of the root, best matches
+    // first
+
+    let hits = search(root, "dentist");
+
+    assert_eq!(stems(&hits), vec!["dentist", "roe"]);
+    assert!(hits[0].score > hits[1].score);
+
+    let hits = search(root, "ACME");
+
+    assert_eq!(stems(&hits), vec!["doe", "roe"]);
+
+    // should match word prefixes, and require all terms
+
+    assert_eq!(stems(&search(root, "dent main")), vec!["dentist"]);
+    assert_eq!(stems(&search(root, "jane dent")), vec!["roe"]);
+    assert!(search(root, "john dentist").is_empty());
+    assert!(search(root, "ohn").is_empty());
+    assert!(search(root, "  ").is_empty());
+
+    // should not search technical properties
+
+    assert!(search(root, "doe")
+        .iter()
+        .all(|hit| hit.matches.iter().all(|m| m.property != "UID")));
+
+    // should highlight the matching words
+
+    let hits = search(root, "acme");
+    let doe = &hits[0];
+
+    let mut highlights: Vec<_> = doe
+        .matches
+        .iter()
+        .map(|m| (m.property.as_str(), m.highlight("[", "]")))
+        .collect();
+    highlights.sort();
+
+    assert_eq!(
+        highlights,
+        vec![
+            ("EMAIL", "john@[acme].example".to_owned()),
+            ("ORG", "[ACME] Inc.".to_owned()),
+        ]
+    );
+
+    let hits = search(root, "dentist appoint");
+
+    assert_eq!(
+        hits[0].matches[0].highlight("*", "*"),
+        "*Dentist* *appointment*"
+    );
+}
@@ -0,0 +1,358 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use io_fs::io::FsIo;
+use io_vdir::{
+    conflict::{ConflictResolution, ConflictStrategy},
+    coroutines::{
+        read_sync_status::{ReadSyncStatus, ReadSyncStatusResult},
+        sync_collection::{SyncCollection, SyncCollectionResult},
+        update_sync_status::{UpdateSyncStatus, UpdateSyncStatusResult},
+    },
+    sync::{SyncChange, SyncReport, SyncSide, SyncStatus},
+};
+use tempfile::tempdir;
+
+use crate::common::{handle, vcard};
+
+mod common;
+
+fn sync(a: &Path, b: &Path, status: SyncStatus) -> SyncReport {
+    sync_with(a, b, status, ConflictStrategy::default())
+}
+
+fn sync_with(a: &Path, b: &Path, status: SyncStatus, strategy: ConflictStrategy) -> SyncReport {
+    let mut arg = None;
+    let mut sync = SyncCollection::new(a, b, status).with_conflict_strategy(strategy);
+    let mut read_paths = HashSet::new();
+
+    loop {
+        match sync.resume(arg) {
+            SyncCollectionResult::Ok(report) => break report,
+            SyncCollectionResult::Io(io) => {
+                // items should be read at most once
+                if let FsIo::ReadFiles(Err(paths)) = &io {
+                    for path in paths {
+                        assert!(read_paths.insert(path.clone()), "{path:?} read twice");
+                    }
+                }
+
+                arg = Some(handle(io))
+            }
+            SyncCollectionResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+/// Synchronizes the given collections, calling the given function
+/// right before processing each I/O in order to simulate concurrent
+/// changes.
+fn sync_racing(a: &Path, b: &Path, status: SyncStatus, mut race: impl FnMut(&FsIo)) -> SyncReport {
+    let mut arg = None;
+    let mut sync = SyncCollection::new(a, b, status);
+
+    loop {
+        match sync.resume(arg) {
+            SyncCollectionResult::Ok(report) => break report,
+            SyncCollectionResult::Io(io) => {
+                race(&io);
+                arg = Some(handle(io))
+            }
+            SyncCollectionResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn read_status(mut coroutine: ReadSyncStatus) -> SyncStatus {
+    let mut arg = None;
+
+    loop {
+        match coroutine.resume(arg) {
+            ReadSyncStatusResult::Ok(status) => break status,
+            ReadSyncStatusResult::Io(io) => arg = Some(handle(io)),
+            ReadSyncStatusResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+fn update_status(path: &Path, status: &SyncStatus) {
+    let mut arg = None;
+    let mut coroutine = UpdateSyncStatus::new(path, status);
+
+    loop {
+        match coroutine.resume(arg) {
+            UpdateSyncStatusResult::Ok => break,
+            UpdateSyncStatusResult::Io(io) => arg = Some(handle(io)),
+            UpdateSyncStatusResult::Err(err) => panic!("{err}"),
+        }
+    }
+}
+
+#[test]
+fn std_sync() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let a = workdir.path().join("a");
+    let b = workdir.path().join("b");
+
+    let status_path = workdir.path().join("status");
+
+    fs::create_dir(&a).unwrap();
+    fs::create_dir(&b).unwrap();
+
+    // should read an empty status when the status file does not exist
+
+    let status = read_status(ReadSyncStatus::new(&status_path));
+
+    assert_eq!(status, SyncStatus::default());
+
+    // should propagate creations in both directions
+
+    fs::write(a.join("x.vcf"), vcard("x", &["FN:X"])).unwrap();
+    fs::write(b.join("y.vcf"), vcard("y", &["FN:Y"])).unwrap();
+
+    let report = sync(&a, &b, status);
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.changes.len(), 2);
+    assert!(report
+        .changes
+        .contains(&SyncChange::Create(SyncSide::B, "x.vcf".into())));
+    assert!(report
+        .changes
+        .contains(&SyncChange::Create(SyncSide::A, "y.vcf".into())));
+    assert_eq!(report.status.items.len(), 2);
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X"])
+    );
+    assert_eq!(
+        fs::read_to_string(a.join("y.vcf")).unwrap(),
+        vcard("y", &["FN:Y"])
+    );
+
+    // should persist the status between synchronizations
+
+    update_status(&status_path, &report.status);
+    let status = read_status(ReadSyncStatus::new(&status_path));
+
+    assert_eq!(status, report.status);
+    assert_eq!(fs::read_dir(workdir.path()).unwrap().count(), 3);
+
+    // should not change anything when already synchronized
+
+    let report = sync(&a, &b, status);
+
+    assert!(report.changes.is_empty());
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.status.items.len(), 2);
+
+    // should propagate updates and deletions
+
+    fs::write(a.join("x.vcf"), vcard("x", &["FN:X2"])).unwrap();
+    fs::remove_file(b.join("y.vcf")).unwrap();
+
+    let report = sync(&a, &b, report.status);
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.changes.len(), 2);
+    assert!(report
+        .changes
+        .contains(&SyncChange::Update(SyncSide::B, "x.vcf".into())));
+    assert!(report
+        .changes
+        .contains(&SyncChange::Delete(SyncSide::A, "y.vcf".into())));
+    assert_eq!(report.status.items.len(), 1);
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X2"])
+    );
+    assert!(!a.join("y.vcf").exists());
+
+    // should not propagate invalid items as deletions
+
+    fs::write(a.join("x.vcf"), "not a vcard").unwrap();
+
+    let status = report.status;
+    let report = sync(&a, &b, status.clone());
+
+    assert!(report.changes.is_empty());
+    assert_eq!(report.status, status);
+    assert!(b.join("x.vcf").exists());
+
+    // should report conflicts
+
+    fs::write(a.join("x.vcf"), vcard("x", &["FN:X3"])).unwrap();
+    fs::write(b.join("x.vcf"), vcard("x", &["FN:X4"])).unwrap();
+
+    let status = report.status;
+    let report = sync(&a, &b, status.clone());
+
+    assert!(report.changes.is_empty());
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].ident, "x");
+    assert_eq!(report.status, status);
+    assert_eq!(
+        fs::read_to_string(a.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X3"])
+    );
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X4"])
+    );
+
+    // should resolve conflicts using the given strategy
+
+    let report = sync_with(&a, &b, report.status, ConflictStrategy::KeepB);
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(
+        report.changes,
+        vec![SyncChange::Update(SyncSide::A, "x.vcf".into())]
+    );
+    assert_eq!(
+        fs::read_to_string(a.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X4"])
+    );
+
+    let report = sync(&a, &b, report.status);
+
+    assert!(report.changes.is_empty());
+    assert!(report.conflicts.is_empty());
+
+    // should resolve conflicts using modification dates
+
+    fs::write(
+        a.join("x.vcf"),
+        vcard("x", &["FN:X5", "REV:20240102T000000Z"]),
+    )
+    .unwrap();
+    fs::write(
+        b.join("x.vcf"),
+        vcard("x", &["FN:X6", "REV:20240101T000000Z"]),
+    )
+    .unwrap();
+
+    let report = sync_with(&a, &b, report.status, ConflictStrategy::Newest);
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(
+        report.changes,
+        vec![SyncChange::Update(SyncSide::B, "x.vcf".into())]
+    );
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X5", "REV:20240102T000000Z"])
+    );
+
+    // should resolve conflicts using a custom resolver
+
+    fs::write(a.join("x.vcf"), vcard("x", &["FN:X7"])).unwrap();
+    fs::write(b.join("x.vcf"), vcard("x", &["FN:X8"])).unwrap();
+
+    let strategy = ConflictStrategy::custom(|_, b| ConflictResolution::Merge(b.kind.clone()));
+    let report = sync_with(&a, &b, report.status, strategy);
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.changes.len(), 2);
+    assert_eq!(
+        fs::read(a.join("x.vcf")).unwrap(),
+        fs::read(b.join("x.vcf")).unwrap()
+    );
+
+    let report = sync(&a, &b, report.status);
+
+    assert!(report.changes.is_empty());
+
+    // should keep both conflicting items
+
+    fs::write(a.join("x.vcf"), vcard("x", &["FN:X9"])).unwrap();
+    fs::write(b.join("x.vcf"), vcard("x", &["FN:X10"])).unwrap();
+
+    let report = sync_with(&a, &b, report.status, ConflictStrategy::KeepBoth);
+
+    assert!(report.conflicts.is_empty());
+    assert_eq!(report.changes.len(), 3);
+    assert_eq!(report.status.items.len(), 2);
+    assert_eq!(fs::read_dir(&a).unwrap().count(), 2);
+    assert_eq!(fs::read_dir(&b).unwrap().count(), 2);
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X9"])
+    );
+
+    let report = sync(&a, &b, report.status);
+
+    assert!(report.changes.is_empty());
+    assert!(report.conflicts.is_empty());
+
+    // should import legacy vdirsyncer JSON status files
+
+    let etag_a = report.status.items["x"].a.etag.to_string();
+    let etag_b = report.status.items["x"].b.etag.to_string();
+    let vdirsyncer_path = workdir.path().join("vdirsyncer.items");
+    let contents = format!(r#"{{"x":["x.vcf","{etag_a}","x.vcf","{etag_b}"]}}"#);
+    fs::write(&vdirsyncer_path, contents).unwrap();
+
+    let status = read_status(ReadSyncStatus::vdirsyncer_json(&vdirsyncer_path));
+
+    assert_eq!(status.items.len(), 1);
+    assert_eq!(status.items["x"], report.status.items["x"]);
+
+    // should not overwrite items modified in the meantime
+
+    fs::write(a.join("x.vcf"), vcard("x", &["FN:X11"])).unwrap();
+
+    let status = report.status;
+    let report = sync_racing(&a, &b, status.clone(), |io| {
+        if let FsIo::CreateFile(Err(_)) = io {
+            fs::write(b.join("x.vcf"), vcard("x", &["FN:X12"])).unwrap();
+        }
+    });
+
+    assert!(report.changes.is_empty());
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].ident, "x");
+    assert_eq!(
+        report.conflicts[0].b.as_ref().map(|item| item.to_bytes()),
+        Some(vcard("x", &["FN:X12"]).into_bytes())
+    );
+    assert_eq!(report.status, status);
+    assert_eq!(fs::read_dir(&b).unwrap().count(), 2);
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X12"])
+    );
+
+    // should not delete items modified in the meantime
+
+    let report = sync_with(&a, &b, report.status, ConflictStrategy::KeepA);
+
+    assert!(report.conflicts.is_empty());
+
+    fs::remove_file(a.join("x.vcf")).unwrap();
+
+    let status = report.status;
+    let report = sync_racing(&a, &b, status.clone(), |io| {
+        if let FsIo::ReadFile(Err(_)) = io {
+            fs::write(b.join("x.vcf"), vcard("x", &["FN:X13"])).unwrap();
+        }
+    });
+
+    assert!(report.changes.is_empty());
+    assert_eq!(report.conflicts.len(), 1);
+    assert!(report.conflicts[0].a.is_none());
+    assert!(report.conflicts[0].b.is_some());
+    assert_eq!(report.status, status);
+    assert_eq!(
+        fs::read_to_string(b.join("x.vcf")).unwrap(),
+        vcard("x", &["FN:X13"])
+    );
+
+    let report = sync(&a, &b, report.status);
+
+    assert_eq!(
+        report.changes,
+        vec![SyncChange::Create(SyncSide::A, "x.vcf".into())]
+    );
+}
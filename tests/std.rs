@@ -1,20 +1,28 @@
-use std::{collections::HashSet, io::ErrorKind};
+#![allow(clippy::iter_count, clippy::needless_borrows_for_generic_args)]
 
-use calcard::vcard::VCard;
-use io_fs::runtimes::std::handle;
+use std::{collections::HashSet, fs, io::ErrorKind};
+
+use calcard::{icalendar::ICalendar, vcard::VCard};
+use io_fs::{io::FsIo, runtimes::std::handle};
 use io_vdir::{
-    collection::Collection,
+    collection::{Collection, CollectionPatch, MetadataPatch},
     coroutines::{
         create_collection::{CreateCollection, CreateCollectionResult},
-        create_item::{CreateItem, CreateItemResult},
+        create_item::{CreateItem, CreateItemError, CreateItemResult},
         delete_collection::{DeleteCollection, DeleteCollectionResult},
-        delete_item::{DeleteItem, DeleteItemResult},
+        delete_item::{DeleteItem, DeleteItemError, DeleteItemResult},
+        find_item_by_uid::{FindItemByUid, FindItemByUidError, FindItemByUidResult},
         list_collections::{ListCollections, ListCollectionsResult},
+        list_item_hrefs::{ListItemHrefs, ListItemHrefsResult},
         list_items::{ListItems, ListItemsResult},
+        read_item::{ReadItem, ReadItemError, ReadItemResult},
+        remove_tmp_files::{RemoveTmpFiles, RemoveTmpFilesResult},
         update_collection::{UpdateCollection, UpdateCollectionResult},
-        update_item::{UpdateItem, UpdateItemResult},
+        update_item::{UpdateItem, UpdateItemError, UpdateItemResult},
     },
-    item::{Item, ItemKind},
+    item::{uid_file_stem, Item, ItemFormat, ItemHref, ItemKind, MAX_UID_FILE_STEM_LEN},
+    runtime::HandBack,
+    tmp::tmp_path,
 };
 use tempfile::tempdir;
 
@@ -38,6 +46,23 @@ fn std() {
 
     assert!(collections.is_empty());
 
+    // should ignore hidden entries
+
+    fs::create_dir(root.join(".hidden")).unwrap();
+
+    let mut arg = None;
+    let mut list = ListCollections::new(root);
+
+    let collections = loop {
+        match list.resume(arg) {
+            ListCollectionsResult::Ok(collections) => break collections,
+            ListCollectionsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListCollectionsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert!(collections.is_empty());
+
     // should create collection without metadata
 
     let mut collection = Collection::new(&root);
@@ -116,6 +141,85 @@ fn std() {
 
     assert_eq!(collections, expected_collections);
 
+    // should ignore non-item files
+
+    fs::write(collection.path.join("notes.txt"), "notes").unwrap();
+
+    let mut arg = None;
+    let mut list = ListItems::new(&collection);
+
+    let items = loop {
+        match list.resume(arg) {
+            ListItemsResult::Ok(items, _) => break items,
+            ListItemsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListItemsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert!(items.is_empty());
+
+    // should unset collection metadata
+
+    let patch = CollectionPatch {
+        description: MetadataPatch::Unset,
+        color: MetadataPatch::Unset,
+        ..Default::default()
+    };
+
+    let mut arg = None;
+    let mut update = UpdateCollection::patch(&collection.path, patch);
+
+    loop {
+        match update.resume(arg) {
+            UpdateCollectionResult::Ok => break,
+            UpdateCollectionResult::Io(io) => arg = Some(handle(io).unwrap()),
+            UpdateCollectionResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    collection.description = None;
+    collection.color = None;
+
+    let mut arg = None;
+    let mut list = ListCollections::new(root);
+
+    let collections = loop {
+        match list.resume(arg) {
+            ListCollectionsResult::Ok(items) => break items,
+            ListCollectionsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListCollectionsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    let expected_collections = HashSet::from_iter([collection.clone()]);
+
+    assert_eq!(collections, expected_collections);
+
+    // should report invalid items
+
+    let invalid_path = collection.path.join("invalid.vcf");
+    fs::write(&invalid_path, "not a vcard").unwrap();
+
+    let mut arg = None;
+    let mut list = ListItems::new(&collection);
+
+    let (items, errors) = loop {
+        match list.resume(arg) {
+            ListItemsResult::Ok(items, errors) => break (items, errors),
+            ListItemsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListItemsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert!(items.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors.get(&invalid_path),
+        Some(ReadItemError::InvalidVcardContents(_, path)) if path == &invalid_path,
+    ));
+
+    fs::remove_file(invalid_path).unwrap();
+
     // should create item
 
     let mut item = Item::new(
@@ -139,7 +243,7 @@ fn std() {
 
     let items = loop {
         match list.resume(arg) {
-            ListItemsResult::Ok(items) => break items,
+            ListItemsResult::Ok(items, _) => break items,
             ListItemsResult::Io(io) => arg = Some(handle(io).unwrap()),
             ListItemsResult::Err(err) => panic!("{err}"),
         }
@@ -147,6 +251,64 @@ fn std() {
 
     assert_eq!(items.len(), 1);
 
+    // should list item hrefs
+
+    let mut arg = None;
+    let mut list = ListItemHrefs::new(&collection).with_etags(true);
+
+    let hrefs = loop {
+        match list.resume(arg) {
+            ListItemHrefsResult::Ok(hrefs) => break hrefs,
+            ListItemHrefsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListItemHrefsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    let expected_href = ItemHref {
+        path: item.path.clone(),
+        format: ItemFormat::Vcard,
+        etag: items.iter().next().unwrap().etag.clone(),
+    };
+
+    assert_eq!(hrefs, HashSet::from_iter([expected_href.clone()]));
+
+    // should list item hrefs without reading items
+
+    let mut arg = None;
+    let mut list = ListItemHrefs::new(&collection);
+
+    let hrefs = loop {
+        match list.resume(arg) {
+            ListItemHrefsResult::Ok(hrefs) => break hrefs,
+            ListItemHrefsResult::Io(FsIo::ReadFiles(_)) => panic!("should not read items"),
+            ListItemHrefsResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ListItemHrefsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    let expected_href = ItemHref {
+        etag: None,
+        ..expected_href
+    };
+
+    assert_eq!(hrefs, HashSet::from_iter([expected_href]));
+
+    // should not re-create existing item
+
+    let mut arg = None;
+    let mut create = CreateItem::new(item.clone());
+
+    loop {
+        match create.resume(arg) {
+            CreateItemResult::Ok => panic!("should fail"),
+            CreateItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            CreateItemResult::Err(CreateItemError::AlreadyExists(path)) => {
+                break assert_eq!(path, item.path)
+            }
+            CreateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
     let first_item = items.into_iter().next().unwrap();
 
     assert_eq!(
@@ -160,7 +322,7 @@ fn std() {
         ItemKind::Vcard(VCard::parse("BEGIN:VCARD\r\nUID: def456\r\nEND:VCARD\r\n").unwrap());
 
     let mut arg = None;
-    let mut update = UpdateItem::new(item);
+    let mut update = UpdateItem::new(item, first_item.etag.clone());
 
     loop {
         match update.resume(arg) {
@@ -175,7 +337,7 @@ fn std() {
 
     let items = loop {
         match list.resume(arg) {
-            ListItemsResult::Ok(items) => break items,
+            ListItemsResult::Ok(items, _) => break items,
             ListItemsResult::Io(io) => arg = Some(handle(io).unwrap()),
             ListItemsResult::Err(err) => panic!("{err}"),
         }
@@ -190,24 +352,59 @@ fn std() {
         "BEGIN:VCARD\r\nVERSION:4.0\r\nUID: def456\r\nEND:VCARD\r\n"
     );
 
-    // // should read item
+    // should read item
 
-    // let mut output = None;
-    // let mut fs = ReadItem::vcard(collection.path(), "item");
+    let mut arg = None;
+    let mut read = ReadItem::new(&item.path);
 
-    // let expected_item = loop {
-    //     match fs.resume(output) {
-    //         Ok(item) => break item,
-    //         Err(input) => output = Some(handle(input).unwrap()),
-    //     }
-    // };
+    let expected_item = loop {
+        match read.resume(arg) {
+            ReadItemResult::Ok(item) => break item,
+            ReadItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ReadItemResult::Err(err) => panic!("{err}"),
+        }
+    };
 
-    // assert_eq!(item, expected_item);
+    assert_eq!(item, expected_item);
+    assert!(item.etag.is_some());
+    assert_ne!(item.etag, first_item.etag);
+
+    // should not update modified item
+
+    let mut arg = None;
+    let mut update = UpdateItem::new(item.clone(), first_item.etag.clone());
+
+    loop {
+        match update.resume(arg) {
+            UpdateItemResult::Ok => panic!("should fail"),
+            UpdateItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            UpdateItemResult::Err(UpdateItemError::PreconditionFailed(path)) => {
+                break assert_eq!(path, item.path)
+            }
+            UpdateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    // should not delete modified item
+
+    let mut arg = None;
+    let mut delete = DeleteItem::new(&item, first_item.etag.clone());
+
+    loop {
+        match delete.resume(arg) {
+            DeleteItemResult::Ok => panic!("should fail"),
+            DeleteItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            DeleteItemResult::Err(DeleteItemError::PreconditionFailed(path)) => {
+                break assert_eq!(path, item.path)
+            }
+            DeleteItemResult::Err(err) => panic!("{err}"),
+        }
+    }
 
     // should delete item
 
     let mut arg = None;
-    let mut delete = DeleteItem::new(item);
+    let mut delete = DeleteItem::new(&item, item.etag.clone());
 
     loop {
         match delete.resume(arg) {
@@ -222,7 +419,7 @@ fn std() {
 
     let items = loop {
         match list.resume(arg) {
-            ListItemsResult::Ok(items) => break items,
+            ListItemsResult::Ok(items, _) => break items,
             ListItemsResult::Io(io) => arg = Some(handle(io).unwrap()),
             ListItemsResult::Err(err) => panic!("{err}"),
         }
@@ -230,6 +427,179 @@ fn std() {
 
     assert_eq!(items.into_iter().count(), 0);
 
+    // should remove stale temporary files
+
+    let stale_path = tmp_path(collection.path.join("stale.vcf"));
+    fs::write(&stale_path, "BEGIN:VCARD").unwrap();
+
+    let mut arg = None;
+    let mut remove = RemoveTmpFiles::new(&collection.path);
+
+    let removed_paths = loop {
+        match remove.resume(arg) {
+            RemoveTmpFilesResult::Ok(paths) => break paths,
+            RemoveTmpFilesResult::Io(io) => arg = Some(handle(io).unwrap()),
+            RemoveTmpFilesResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert_eq!(removed_paths, HashSet::from_iter([stale_path.clone()]));
+    assert!(!stale_path.exists());
+
+    // should name items after their UID, then find them by UID
+
+    let vcard = |uid: &str| format!("BEGIN:VCARD\r\nVERSION:4.0\r\nUID:{uid}\r\nEND:VCARD\r\n");
+    let named_path = collection
+        .path
+        .join(format!("{}.vcf", uid_file_stem("a/b@c")));
+    let scanned_path = collection.path.join("scanned.vcf");
+
+    assert_eq!(uid_file_stem("a/b@c"), "a%2Fb%40c");
+    assert_eq!(uid_file_stem(".abc+1"), "%2Eabc+1");
+
+    let long_uid = "@".repeat(MAX_UID_FILE_STEM_LEN);
+    let long_stem = uid_file_stem(&long_uid);
+    assert_eq!(long_stem.len(), 40);
+    assert_eq!(long_stem, uid_file_stem(&long_uid));
+    assert_ne!(long_stem, uid_file_stem(&"#".repeat(MAX_UID_FILE_STEM_LEN)));
+
+    let kind = ItemKind::Vcard(VCard::parse(vcard("a/b@c")).unwrap());
+    let item = Item::new(&collection, kind);
+
+    let mut arg = None;
+    let mut create = CreateItem::new(item).with_uid_file_name(true);
+
+    assert_eq!(create.path(), named_path);
+
+    loop {
+        match create.resume(arg) {
+            CreateItemResult::Ok => break,
+            CreateItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            CreateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    assert!(named_path.exists());
+
+    let kind = ItemKind::Vcard(VCard::parse(vcard("a/b@c")).unwrap());
+    let item = Item::new(&collection, kind);
+
+    let mut arg = None;
+    let mut create = CreateItem::new(item).with_unique_uid(true);
+
+    loop {
+        match create.resume(arg) {
+            CreateItemResult::Ok => panic!("should fail"),
+            CreateItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            CreateItemResult::Err(CreateItemError::UidAlreadyExists(uid, path)) => {
+                assert_eq!(uid, "a/b@c");
+                break assert_eq!(path, named_path);
+            }
+            CreateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    let kind = ItemKind::Vcard(VCard::parse("BEGIN:VCARD\r\nEND:VCARD\r\n").unwrap());
+    let item = Item::named_after_uid(&collection, kind);
+
+    assert_eq!(item.path.parent(), Some(collection.path.as_path()));
+    assert_eq!(item.path.extension().unwrap(), "vcf");
+
+    // should assign a UID to items lacking one
+
+    let create = CreateItem::new(item)
+        .with_auto_uid(true)
+        .with_uid_file_name(true);
+
+    let uid = create.item().kind.uid().unwrap();
+    let expected_path = collection.path.join(format!("{}.vcf", uid_file_stem(uid)));
+
+    assert_eq!(create.path(), expected_path);
+
+    let ical = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:abc\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nRECURRENCE-ID:20240101T000000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    let mut kind = ItemKind::Ical(ICalendar::parse(ical).unwrap());
+
+    assert!(kind.ensure_uid());
+    assert!(!kind.ensure_uid());
+
+    let ItemKind::Ical(ical) = &kind else {
+        unreachable!()
+    };
+
+    assert_eq!(ical.uids().collect::<Vec<_>>(), vec!["abc", "abc"]);
+
+    fs::write(&scanned_path, vcard("def")).unwrap();
+    fs::write(collection.path.join("dup1.vcf"), vcard("dup")).unwrap();
+    fs::write(collection.path.join("dup2.vcf"), vcard("dup")).unwrap();
+
+    let find = |uid: &str| {
+        let mut arg = None;
+        let mut find = FindItemByUid::new(&collection, uid);
+
+        loop {
+            match find.resume(arg) {
+                FindItemByUidResult::Ok(item) => break Ok(item),
+                FindItemByUidResult::Io(io) => arg = Some(handle(io).unwrap()),
+                FindItemByUidResult::Err(err) => break Err(err),
+            }
+        }
+    };
+
+    assert_eq!(find("a/b@c").unwrap().path, named_path);
+    assert_eq!(find("def").unwrap().path, scanned_path);
+    assert!(matches!(
+        find("dup"),
+        Err(FindItemByUidError::Duplicated(uid, paths)) if uid == "dup" && paths.len() == 2
+    ));
+    assert!(matches!(
+        find("ghi"),
+        Err(FindItemByUidError::NotFound(uid)) if uid == "ghi"
+    ));
+
+    for path in fs::read_dir(&collection.path).unwrap() {
+        fs::remove_file(path.unwrap().path()).unwrap();
+    }
+
+    // should write back the original contents of unmodified items
+
+    let raw = "BEGIN:VCARD\r\nUID:raw\r\nfn:Doe\r\nEND:VCARD\r\n";
+    let raw_path = collection.path.join("raw.vcf");
+    fs::write(&raw_path, raw).unwrap();
+
+    let mut arg = None;
+    let mut read = ReadItem::new(&raw_path);
+
+    let mut item = loop {
+        match read.resume(arg) {
+            ReadItemResult::Ok(item) => break item,
+            ReadItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            ReadItemResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert!(!item.is_modified());
+    assert_ne!(item.to_string(), raw);
+
+    let mut arg = None;
+    let mut update = UpdateItem::new(item.clone(), item.etag.clone());
+
+    loop {
+        match update.resume(arg) {
+            UpdateItemResult::Ok => break,
+            UpdateItemResult::Io(io) => arg = Some(handle(io).unwrap()),
+            UpdateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    assert_eq!(fs::read_to_string(&raw_path).unwrap(), raw);
+
+    item.kind.set_uid("raw2");
+
+    assert!(item.is_modified());
+    assert_eq!(item.to_bytes(), item.to_string().into_bytes());
+
+    fs::remove_file(&raw_path).unwrap();
+
     // should delete collection
 
     let mut arg = None;
@@ -256,3 +626,151 @@ fn std() {
 
     assert!(collections.is_empty());
 }
+
+#[test]
+fn std_unexpected_entries() {
+    let workdir = tempdir().unwrap();
+    let root = workdir.path();
+
+    // runtimes cannot report failures to coroutines, so failed
+    // requests are handed back unprocessed
+    let mut runtime = HandBack::new(handle);
+    let mut handle = |io: FsIo| runtime.handle(io).unwrap();
+
+    let collection = Collection::new(root);
+
+    let mut arg = None;
+    let mut create = CreateCollection::new(collection.clone());
+
+    loop {
+        match create.resume(arg) {
+            CreateCollectionResult::Ok => break,
+            CreateCollectionResult::Io(io) => arg = Some(handle(io)),
+            CreateCollectionResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    // should skip regular files in the root
+
+    fs::write(root.join("README"), "readme").unwrap();
+
+    let mut arg = None;
+    let mut list = ListCollections::new(root);
+
+    let collections = loop {
+        match list.resume(arg) {
+            ListCollectionsResult::Ok(collections) => break collections,
+            ListCollectionsResult::Io(io) => arg = Some(handle(io)),
+            ListCollectionsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert_eq!(collections, HashSet::from_iter([collection.clone()]));
+
+    // should not hand back requests that cannot be skipped
+
+    let mut runtime = HandBack::new(io_fs::runtimes::std::handle);
+    let mut arg = None;
+    let mut read = ReadItem::new(root.join("missing.vcf"));
+
+    let err = loop {
+        match read.resume(arg) {
+            ReadItemResult::Ok(_) => panic!("should fail"),
+            ReadItemResult::Io(io) => match runtime.handle(io) {
+                Ok(io) => arg = Some(io),
+                Err(err) => break err,
+            },
+            ReadItemResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // should skip directories named like items
+
+    let path = collection.path.join("contact.vcf");
+    fs::write(
+        &path,
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:contact\r\nEND:VCARD\r\n",
+    )
+    .unwrap();
+    fs::create_dir(collection.path.join("dir.vcf")).unwrap();
+
+    let mut arg = None;
+    let mut list = ListItems::new(&collection);
+
+    let (items, errors) = loop {
+        match list.resume(arg) {
+            ListItemsResult::Ok(items, errors) => break (items, errors),
+            ListItemsResult::Io(io) => arg = Some(handle(io)),
+            ListItemsResult::Err(err) => panic!("{err}"),
+        }
+    };
+
+    assert_eq!(items.len(), 1);
+    assert!(errors.is_empty());
+
+    let mut item = items.into_iter().next().unwrap();
+    assert_eq!(item.path, path);
+
+    // should clean up when the item is deleted in the meantime
+
+    let etag = item.etag.take();
+    fs::remove_file(&item.path).unwrap();
+
+    let mut arg = None;
+    let mut update = UpdateItem::new(item.clone(), etag.clone());
+
+    loop {
+        match update.resume(arg) {
+            UpdateItemResult::Ok => panic!("should fail"),
+            UpdateItemResult::Io(io) => arg = Some(handle(io)),
+            UpdateItemResult::Err(UpdateItemError::PreconditionFailed(path)) => {
+                break assert_eq!(path, item.path)
+            }
+            UpdateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    let entries: Vec<_> = fs::read_dir(&collection.path).unwrap().collect();
+    assert_eq!(entries.len(), 1);
+
+    let mut arg = None;
+    let mut delete = DeleteItem::new(&item.path, etag);
+
+    loop {
+        match delete.resume(arg) {
+            DeleteItemResult::Ok => panic!("should fail"),
+            DeleteItemResult::Io(io) => arg = Some(handle(io)),
+            DeleteItemResult::Err(DeleteItemError::PreconditionFailed(path)) => {
+                break assert_eq!(path, item.path)
+            }
+            DeleteItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    // should name items after long UIDs
+
+    let uid = "@".repeat(300);
+    let vcard = format!("BEGIN:VCARD\r\nVERSION:4.0\r\nUID:{uid}\r\nEND:VCARD\r\n");
+    let kind = ItemKind::Vcard(VCard::parse(vcard).unwrap());
+    let item = Item::new(&collection, kind);
+
+    let mut arg = None;
+    let mut create = CreateItem::new(item).with_uid_file_name(true);
+    let path = create.path().to_owned();
+
+    loop {
+        match create.resume(arg) {
+            CreateItemResult::Ok => break,
+            CreateItemResult::Io(io) => arg = Some(handle(io)),
+            CreateItemResult::Err(err) => panic!("{err}"),
+        }
+    }
+
+    assert_eq!(
+        path,
+        collection.path.join(format!("{}.vcf", uid_file_stem(&uid)))
+    );
+    assert!(path.exists());
+}
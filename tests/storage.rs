@@ -0,0 +1,77 @@
+use std::{fs, path::Path};
+
+use io_fs::runtimes::std::handle;
+use io_vdir::{
+    collection::Collection,
+    item::Item,
+    storage::{memory::MemoryStorage, vdir::VdirStorage, Storage},
+};
+use tempfile::tempdir;
+
+use crate::common::vcard_kind;
+
+mod common;
+
+fn exercise<S: Storage>(storage: &mut S, collection: &Collection) {
+    let collections = storage.list_collections().unwrap();
+
+    assert_eq!(collections.len(), 1);
+    assert!(collections.contains(collection));
+
+    // should put and get items
+
+    let mut item = Item::new(collection, vcard_kind("abc", &["FN:Doe"]));
+    let etag = storage.put(&item, None).unwrap();
+
+    let hrefs = storage.list_hrefs(&collection.path).unwrap();
+
+    assert_eq!(hrefs.len(), 1);
+    assert_eq!(hrefs.iter().next().unwrap().etag.as_ref(), Some(&etag));
+
+    let stored = storage.get(&item.path).unwrap();
+
+    assert_eq!(stored.kind, item.kind);
+    assert_eq!(stored.etag.as_ref(), Some(&etag));
+
+    // should replace items matching the given etag only
+
+    item.kind = vcard_kind("abc", &["FN:John Doe"]);
+    let new_etag = storage.put(&item, Some(&etag)).unwrap();
+
+    assert_ne!(new_etag, etag);
+    assert!(storage.put(&item, Some(&etag)).is_err());
+    assert_eq!(storage.get(&item.path).unwrap().kind, item.kind);
+
+    // should delete items matching the given etag only
+
+    assert!(storage.delete(&item.path, Some(&etag)).is_err());
+    storage.delete(&item.path, Some(&new_etag)).unwrap();
+
+    assert!(storage.list_hrefs(&collection.path).unwrap().is_empty());
+    assert!(storage.get(&item.path).is_err());
+}
+
+#[test]
+fn vdir_storage() {
+    let _ = env_logger::try_init();
+
+    let workdir = tempdir().unwrap();
+    let root = workdir.path();
+    let collection = Collection::new(root);
+    fs::create_dir(&collection.path).unwrap();
+    fs::write(root.join("README"), "readme").unwrap();
+
+    let mut storage = VdirStorage::new(root, handle);
+    exercise(&mut storage, &collection);
+}
+
+#[test]
+fn memory_storage() {
+    let _ = env_logger::try_init();
+
+    let collection = Collection::new(Path::new("/"));
+
+    let mut storage = MemoryStorage::new();
+    storage.insert_collection(collection.clone());
+    exercise(&mut storage, &collection);
+}